@@ -0,0 +1,172 @@
+// Counter with CBC-MAC as specified in NIST SP 800-38C and RFC 3610.
+//
+// The tag is a CBC-MAC over a header block containing the nonce and the
+// message length, the associated data and the cleartext. The cleartext is
+// encrypted in CTR mode, the first counter block is used to encrypt the tag.
+
+use crate::ctr::{aes_ctr_from, CounterLayout};
+use crate::{encrypt_aes_block, Aes, AesError, KeySize, BLOCK_SIZE};
+use cipher::constant_time_eq;
+use xor::XOR;
+
+// The nonce can have between 7 and 13 bytes. The remaining bytes of a
+// counter block encode the message length, so shorter nonces allow for
+// longer messages.
+pub fn encrypt_aes_ccm(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    tag_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+    check_parameters(key, nonce, input.len(), tag_len)?;
+    let tag = cbc_mac(input, key, nonce, aad, tag_len)?;
+    let ciphertext = aes_ctr_from(
+        input,
+        key,
+        &counter_block(nonce, 1),
+        CounterLayout::BigEndian128,
+    )?;
+    let s0 = encrypt_aes_block(&counter_block(nonce, 0), key)?;
+    Ok((ciphertext, tag.xor(&s0[..tag_len])))
+}
+
+pub fn decrypt_aes_ccm(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>, AesError> {
+    check_parameters(key, nonce, input.len(), tag.len())?;
+    let cleartext = aes_ctr_from(
+        input,
+        key,
+        &counter_block(nonce, 1),
+        CounterLayout::BigEndian128,
+    )?;
+    let s0 = encrypt_aes_block(&counter_block(nonce, 0), key)?;
+    let expected_tag = cbc_mac(&cleartext, key, nonce, aad, tag.len())?.xor(&s0[..tag.len()]);
+    if !constant_time_eq(&expected_tag, tag) {
+        return Err(AesError::AuthenticationFailed);
+    }
+    Ok(cleartext)
+}
+
+fn check_parameters(
+    key: &[u8],
+    nonce: &[u8],
+    input_len: usize,
+    tag_len: usize,
+) -> Result<(), AesError> {
+    KeySize::from_key_len(key.len())?;
+    if !(7..=13).contains(&nonce.len()) {
+        return Err(AesError::InvalidNonceLength { len: nonce.len() });
+    }
+    if !(4..=16).contains(&tag_len) || !tag_len.is_multiple_of(2) {
+        return Err(AesError::InvalidParameter);
+    }
+    let length_len = BLOCK_SIZE - 1 - nonce.len();
+    if length_len < 8 && (input_len as u64) >> (8 * length_len) != 0 {
+        return Err(AesError::InvalidParameter);
+    }
+    Ok(())
+}
+
+// Flags byte followed by the nonce and the counter in big-endian order
+fn counter_block(nonce: &[u8], counter: u64) -> Vec<u8> {
+    let length_len = BLOCK_SIZE - 1 - nonce.len();
+    let mut block = vec![(length_len - 1) as u8];
+    block.extend_from_slice(nonce);
+    block.extend_from_slice(&counter.to_be_bytes()[8 - length_len..]);
+    block
+}
+
+fn cbc_mac(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    tag_len: usize,
+) -> Result<Vec<u8>, AesError> {
+    let length_len = BLOCK_SIZE - 1 - nonce.len();
+    let flags = (if aad.is_empty() { 0 } else { 0x40 })
+        | (((tag_len - 2) / 2) << 3) as u8
+        | (length_len - 1) as u8;
+    let mut data = vec![flags];
+    data.extend_from_slice(nonce);
+    data.extend_from_slice(&(input.len() as u64).to_be_bytes()[8 - length_len..]);
+
+    if !aad.is_empty() {
+        let len = aad.len() as u64;
+        if len < 0xff00 {
+            data.extend_from_slice(&(len as u16).to_be_bytes());
+        } else if len <= u64::from(u32::MAX) {
+            data.extend_from_slice(&[0xff, 0xfe]);
+            data.extend_from_slice(&(len as u32).to_be_bytes());
+        } else {
+            data.extend_from_slice(&[0xff, 0xff]);
+            data.extend_from_slice(&len.to_be_bytes());
+        }
+        data.extend_from_slice(aad);
+        pad_with_zeros(&mut data);
+    }
+    data.extend_from_slice(input);
+    pad_with_zeros(&mut data);
+
+    let aes = Aes::new(key)?;
+    let mut mac = vec![0; BLOCK_SIZE];
+    for block in data.chunks(BLOCK_SIZE) {
+        mac = aes.encrypt(&mac.xor(block))?;
+    }
+    mac.truncate(tag_len);
+    Ok(mac)
+}
+
+fn pad_with_zeros(data: &mut Vec<u8>) {
+    let len = data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    data.resize(len, 0);
+}
+
+#[test]
+fn test_ccm() {
+    use serialize::from_hex;
+
+    // Examples 1 and 2 from NIST SP 800-38C, Appendix C
+    let key = from_hex("404142434445464748494a4b4c4d4e4f").unwrap();
+    let vectors = [
+        (
+            "10111213141516",
+            "0001020304050607",
+            "20212223",
+            "7162015b",
+            "4dac255d",
+        ),
+        (
+            "1011121314151617",
+            "000102030405060708090a0b0c0d0e0f",
+            "202122232425262728292a2b2c2d2e2f",
+            "d2a1f0e051ea5f62081a7792073d593d",
+            "1fc64fbfaccd",
+        ),
+    ];
+    for &(nonce, aad, cleartext, ciphertext, tag) in vectors.iter() {
+        let nonce = from_hex(nonce).unwrap();
+        let aad = from_hex(aad).unwrap();
+        let cleartext = from_hex(cleartext).unwrap();
+        let ciphertext = from_hex(ciphertext).unwrap();
+        let tag = from_hex(tag).unwrap();
+        assert_eq!(
+            (ciphertext.clone(), tag.clone()),
+            encrypt_aes_ccm(&cleartext, &key, &nonce, &aad, tag.len()).unwrap()
+        );
+        assert_eq!(
+            cleartext,
+            decrypt_aes_ccm(&ciphertext, &key, &nonce, &aad, &tag).unwrap()
+        );
+        match decrypt_aes_ccm(&ciphertext, &key, &nonce, &[], &tag) {
+            Err(AesError::AuthenticationFailed) => (),
+            _ => panic!("expected AuthenticationFailed"),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// CMAC (also known as OMAC1) as specified in NIST SP 800-38B and RFC 4493.
+
+use crate::{Aes, AesError, BLOCK_SIZE};
+use xor::XOR;
+
+pub fn cmac(key: &[u8], message: &[u8]) -> Result<Vec<u8>, AesError> {
+    let aes = Aes::new(key)?;
+
+    let k1 = dbl(&aes.encrypt(&[0; BLOCK_SIZE])?);
+    let k2 = dbl(&k1);
+
+    // The last block is xor'ed with k1 if it is complete, otherwise it is
+    // padded with 0x80 0x00 ... 0x00 and xor'ed with k2. An empty message
+    // consists of a single incomplete block.
+    let last_start = if message.is_empty() {
+        0
+    } else {
+        (message.len() - 1) / BLOCK_SIZE * BLOCK_SIZE
+    };
+    let last = &message[last_start..];
+    let last = if last.len() == BLOCK_SIZE {
+        last.xor(&k1)
+    } else {
+        let mut padded = last.to_vec();
+        padded.push(0x80);
+        padded.resize(BLOCK_SIZE, 0);
+        padded.xor(&k2)
+    };
+
+    let mut mac = vec![0; BLOCK_SIZE];
+    for block in message[..last_start].chunks(BLOCK_SIZE) {
+        mac = aes.encrypt(&mac.xor(block))?;
+    }
+    aes.encrypt(&mac.xor(&last))
+}
+
+// Multiplication by x in GF(2^128) with big-endian bit order, as used for
+// the subkeys of CMAC and in S2V.
+pub(crate) fn dbl(block: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; BLOCK_SIZE];
+    let mut carry = 0;
+    for (r, &b) in result.iter_mut().zip(block.iter()).rev() {
+        *r = (b << 1) | carry;
+        carry = b >> 7;
+    }
+    if carry != 0 {
+        result[BLOCK_SIZE - 1] ^= 0x87;
+    }
+    result
+}
+
+#[test]
+fn test_cmac() {
+    use serialize::from_hex;
+
+    // Test vectors from RFC 4493, section 4
+    let key = from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+    let message = from_hex(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411",
+    )
+    .unwrap();
+    let vectors = [
+        (0, "bb1d6929e95937287fa37d129b756746"),
+        (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+        (40, "dfa66747de9ae63030ca32611497c827"),
+    ];
+    for &(len, mac) in vectors.iter() {
+        assert_eq!(from_hex(mac).unwrap(), cmac(&key, &message[..len]).unwrap());
+    }
+}
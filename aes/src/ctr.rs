@@ -0,0 +1,252 @@
+// CTR mode with a caller-supplied nonce and initial block counter. The
+// counter block consists of the nonce followed by the counter, whose width
+// and byte order are determined by a CounterLayout.
+
+use crate::{encrypt_aes_blocks, AesError, KeySize, BLOCK_SIZE};
+use cipher::{add_to_counter_be, add_to_counter_le, StreamCipher};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CounterLayout {
+    // 8 byte nonce followed by a 64-bit little-endian counter. This is the
+    // layout used by the cryptopals challenges and by MODE::CTR.
+    LittleEndian64,
+    // 12 byte nonce followed by a 32-bit big-endian counter as in GCM and
+    // RFC 3686.
+    BigEndian32,
+    // No nonce, the entire block is a 128-bit big-endian counter as in
+    // NIST SP 800-38A.
+    BigEndian128,
+}
+
+impl CounterLayout {
+    pub fn counter_len(self) -> usize {
+        match self {
+            CounterLayout::LittleEndian64 => 8,
+            CounterLayout::BigEndian32 => 4,
+            CounterLayout::BigEndian128 => 16,
+        }
+    }
+
+    pub fn nonce_len(self) -> usize {
+        BLOCK_SIZE - self.counter_len()
+    }
+
+    pub fn counter_block(self, nonce: &[u8], counter: u128) -> Result<Vec<u8>, AesError> {
+        if nonce.len() != self.nonce_len() {
+            return Err(AesError::InvalidNonceLength { len: nonce.len() });
+        }
+        if self.counter_len() < BLOCK_SIZE && counter >> (8 * self.counter_len()) != 0 {
+            return Err(AesError::InvalidParameter);
+        }
+
+        let mut block = nonce.to_vec();
+        match self {
+            CounterLayout::LittleEndian64 => {
+                block.extend_from_slice(&(counter as u64).to_le_bytes())
+            }
+            CounterLayout::BigEndian32 => block.extend_from_slice(&(counter as u32).to_be_bytes()),
+            CounterLayout::BigEndian128 => block.extend_from_slice(&counter.to_be_bytes()),
+        }
+        Ok(block)
+    }
+
+    // An overflow wraps the counter around to zero. The nonce part of the
+    // block is never modified.
+    pub fn increment(self, block: &mut [u8]) {
+        self.add(block, 1);
+    }
+
+    // Advances the counter in block by n, wrapping around on overflow.
+    pub fn add(self, block: &mut [u8], n: u128) {
+        let counter = &mut block[self.nonce_len()..];
+        match self {
+            CounterLayout::LittleEndian64 => add_to_counter_le(counter, n),
+            CounterLayout::BigEndian32 | CounterLayout::BigEndian128 => {
+                add_to_counter_be(counter, n)
+            }
+        }
+    }
+}
+
+// Encryption and decryption are the same operation in CTR mode.
+pub fn aes_ctr_with_nonce(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    initial_counter: u128,
+    layout: CounterLayout,
+) -> Result<Vec<u8>, AesError> {
+    KeySize::from_key_len(key.len())?;
+    let counter = layout.counter_block(nonce, initial_counter)?;
+    aes_ctr_from(input, key, &counter, layout)
+}
+
+// A CTR keystream with random access. The keystream can be applied at
+// arbitrary byte offsets, and only the blocks covering the affected range are
+// computed. Sequential access goes through StreamCipher.
+#[derive(Clone)]
+pub struct Ctr {
+    key: Vec<u8>,
+    layout: CounterLayout,
+    initial_counter: Vec<u8>,
+    position: u64,
+}
+
+impl Ctr {
+    pub fn new(
+        key: &[u8],
+        nonce: &[u8],
+        initial_counter: u128,
+        layout: CounterLayout,
+    ) -> Result<Self, AesError> {
+        KeySize::from_key_len(key.len())?;
+        Ok(Ctr {
+            key: key.to_vec(),
+            layout,
+            initial_counter: layout.counter_block(nonce, initial_counter)?,
+            position: 0,
+        })
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    // Applies the keystream starting at byte `offset` of the stream. The
+    // current position is not changed.
+    pub fn apply_keystream_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), AesError> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        let block_size = BLOCK_SIZE as u64;
+        let mut counter = self.initial_counter.clone();
+        self.layout
+            .add(&mut counter, u128::from(offset / block_size));
+
+        let skip = (offset % block_size) as usize;
+        let blocks_count = (skip + buf.len()).div_ceil(BLOCK_SIZE);
+        let keystream = keystream(&self.key, &counter, self.layout, blocks_count)?;
+        for (b, k) in buf.iter_mut().zip(keystream[skip..].iter()) {
+            *b ^= k;
+        }
+        Ok(())
+    }
+}
+
+// Applies the keystream at the current position and advances the position by
+// the length of buf.
+impl StreamCipher for Ctr {
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        // unwrap is ok, the key length has been checked by `new`
+        self.apply_keystream_at(self.position, buf).unwrap();
+        self.position += buf.len() as u64;
+    }
+}
+
+pub(crate) fn aes_ctr_from(
+    input: &[u8],
+    key: &[u8],
+    initial_counter: &[u8],
+    layout: CounterLayout,
+) -> Result<Vec<u8>, AesError> {
+    let blocks_count = input.len().div_ceil(BLOCK_SIZE);
+    let keystream = keystream(key, initial_counter, layout, blocks_count)?;
+    Ok(input
+        .iter()
+        .zip(keystream.iter())
+        .map(|(b, k)| b ^ k)
+        .collect())
+}
+
+// Computes the given number of keystream blocks with a single call to the
+// block cipher.
+fn keystream(
+    key: &[u8],
+    initial_counter: &[u8],
+    layout: CounterLayout,
+    blocks_count: usize,
+) -> Result<Vec<u8>, AesError> {
+    let mut counters = Vec::with_capacity(blocks_count * BLOCK_SIZE);
+    let mut counter = initial_counter.to_vec();
+    for _ in 0..blocks_count {
+        counters.extend_from_slice(&counter);
+        layout.increment(&mut counter);
+    }
+    encrypt_aes_blocks(&counters, key)
+}
+
+#[test]
+fn test_ctr_layouts() {
+    use serialize::from_hex;
+
+    // Test vector #2 from RFC 3686
+    let key = from_hex("7e24067817fae0d743d6ce1f32539163").unwrap();
+    let nonce = from_hex("006cb6dbc0543b59da48d90b").unwrap();
+    let cleartext: Vec<u8> = (0..32).collect();
+    let ciphertext =
+        from_hex("5104a106168a72d9790d41ee8edad388eb2e1efc46da57c8fce630df9141be28").unwrap();
+    assert_eq!(
+        ciphertext,
+        aes_ctr_with_nonce(&cleartext, &key, &nonce, 1, CounterLayout::BigEndian32).unwrap()
+    );
+
+    // Test vector F.5.1 from NIST SP 800-38A
+    let key = from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+    let cleartext =
+        from_hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51").unwrap();
+    let ciphertext =
+        from_hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff").unwrap();
+    assert_eq!(
+        ciphertext,
+        aes_ctr_with_nonce(
+            &cleartext,
+            &key,
+            &[],
+            0xf0f1_f2f3_f4f5_f6f7_f8f9_fafb_fcfd_feff,
+            CounterLayout::BigEndian128
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_ctr_counter_overflow() {
+    let key = [0; 16];
+    let nonce = [1; 12];
+    let input = [0; 2 * BLOCK_SIZE];
+    let layout = CounterLayout::BigEndian32;
+    let keystream = aes_ctr_with_nonce(&input, &key, &nonce, 0xffff_ffff, layout).unwrap();
+    assert_eq!(
+        &aes_ctr_with_nonce(&input[..BLOCK_SIZE], &key, &nonce, 0, layout).unwrap()[..],
+        &keystream[BLOCK_SIZE..]
+    );
+    assert!(aes_ctr_with_nonce(&input, &key, &nonce, 1 << 32, layout).is_err());
+}
+
+#[test]
+fn test_ctr_random_access() {
+    let key = [3; 16];
+    let nonce = [4; 8];
+    let layout = CounterLayout::LittleEndian64;
+    let cleartext: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let ciphertext = aes_ctr_with_nonce(&cleartext, &key, &nonce, 0, layout).unwrap();
+
+    let mut ctr = Ctr::new(&key, &nonce, 0, layout).unwrap();
+    for &(offset, len) in [(0, 200), (5, 3), (13, 40), (32, 16), (199, 1), (100, 0)].iter() {
+        let mut buf = cleartext[offset..offset + len].to_vec();
+        ctr.apply_keystream_at(offset as u64, &mut buf).unwrap();
+        assert_eq!(&ciphertext[offset..offset + len], &buf[..]);
+    }
+
+    ctr.seek(17);
+    let mut buf = cleartext[17..60].to_vec();
+    ctr.apply_keystream(&mut buf[..20]);
+    ctr.apply_keystream(&mut buf[20..]);
+    assert_eq!(60, ctr.position());
+    assert_eq!(&ciphertext[17..60], &buf[..]);
+}
@@ -0,0 +1,157 @@
+// CBC mode with ciphertext stealing as specified in the addendum to
+// NIST SP 800-38A. The ciphertext has exactly the length of the cleartext,
+// which needs to be at least one block long.
+//
+// All three variants compute the same blocks. The last cleartext block is
+// padded with zeros before encryption, and the unneeded bytes of the
+// penultimate ciphertext block are dropped. The variants only differ in the
+// order of the last two ciphertext blocks:
+//
+// CS1: C_1 ... C_{n-2} C_{n-1}* C_n
+// CS2: as CS1 if the cleartext is a multiple of the block size, as CS3 otherwise
+// CS3: C_1 ... C_{n-2} C_n C_{n-1}* (this is the variant used by Kerberos)
+
+use crate::{decrypt_aes_block, decrypt_aes_cbc, encrypt_aes_cbc, AesError, Padding, BLOCK_SIZE};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CtsVariant {
+    CS1,
+    CS2,
+    CS3,
+}
+
+impl CtsVariant {
+    // Returns true if the last two blocks are swapped compared to CS1 for
+    // an input of length len.
+    fn swaps_last_blocks(self, len: usize) -> bool {
+        len > BLOCK_SIZE
+            && match self {
+                CtsVariant::CS1 => false,
+                CtsVariant::CS2 => !len.is_multiple_of(BLOCK_SIZE),
+                CtsVariant::CS3 => true,
+            }
+    }
+}
+
+pub(crate) fn encrypt_aes_cts(
+    input: &[u8],
+    key: &[u8],
+    iv: &[u8],
+    variant: CtsVariant,
+) -> Result<Vec<u8>, AesError> {
+    let n = input.len();
+    if n < BLOCK_SIZE {
+        return Err(AesError::InputTooShort);
+    }
+
+    let d = last_block_len(n);
+    let mut padded = input.to_vec();
+    padded.resize(n + BLOCK_SIZE - d, 0);
+    let mut ciphertext = encrypt_aes_cbc(&padded, key, iv, Padding::NoPadding)?;
+    let len = ciphertext.len();
+    if len >= 2 * BLOCK_SIZE {
+        ciphertext.drain(len - 2 * BLOCK_SIZE + d..len - BLOCK_SIZE);
+    }
+
+    if variant.swaps_last_blocks(n) {
+        ciphertext[n - BLOCK_SIZE - d..].rotate_left(d);
+    }
+    Ok(ciphertext)
+}
+
+pub(crate) fn decrypt_aes_cts(
+    input: &[u8],
+    key: &[u8],
+    iv: &[u8],
+    variant: CtsVariant,
+) -> Result<Vec<u8>, AesError> {
+    let n = input.len();
+    if n < BLOCK_SIZE {
+        return Err(AesError::InputTooShort);
+    }
+
+    let d = last_block_len(n);
+    let mut ciphertext = input.to_vec();
+    if variant.swaps_last_blocks(n) {
+        ciphertext[n - BLOCK_SIZE - d..].rotate_right(d);
+    }
+
+    // Decrypting C_n yields C_{n-1} xor (P_n* || 0...0), so the missing
+    // bytes of C_{n-1} can be read off from the end of this block.
+    if n > BLOCK_SIZE {
+        let z = decrypt_aes_block(&ciphertext[n - BLOCK_SIZE..], key)?;
+        let last = ciphertext.split_off(n - BLOCK_SIZE);
+        ciphertext.extend_from_slice(&z[d..]);
+        ciphertext.extend_from_slice(&last);
+    }
+
+    let mut cleartext = decrypt_aes_cbc(&ciphertext, key, iv, Padding::NoPadding)?;
+    cleartext.truncate(n);
+    Ok(cleartext)
+}
+
+// Length of the last, possibly partial, block of an input of length n
+fn last_block_len(n: usize) -> usize {
+    match n % BLOCK_SIZE {
+        0 => BLOCK_SIZE,
+        r => r,
+    }
+}
+
+#[test]
+fn test_cts() {
+    use crate::{Aes128, MODE};
+    use serialize::from_hex;
+
+    // Test vectors from RFC 3962, Appendix B (Kerberos uses CS3)
+    let key = b"chicken teriyaki";
+    let iv = [0; BLOCK_SIZE];
+    let vectors = [
+        (
+            "4920776f756c64206c696b652074686520",
+            "c6353568f2bf8cb4d8a580362da7ff7f97",
+        ),
+        (
+            "4920776f756c64206c696b65207468652047656e6572616c20476175277320",
+            "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+        ),
+        (
+            "4920776f756c64206c696b65207468652047656e6572616c2047617527732043",
+            "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+        ),
+    ];
+    for &(cleartext, ciphertext) in vectors.iter() {
+        let cleartext = from_hex(cleartext).unwrap();
+        let ciphertext = from_hex(ciphertext).unwrap();
+        let mode = MODE::CTS(CtsVariant::CS3);
+        assert_eq!(ciphertext, cleartext.encrypt(key, Some(&iv), mode).unwrap());
+        assert_eq!(cleartext, ciphertext.decrypt(key, Some(&iv), mode).unwrap());
+    }
+
+    let cleartext: Vec<u8> = (0..64).collect();
+    for len in BLOCK_SIZE..=cleartext.len() {
+        let cbc = cleartext[..len]
+            .encrypt_with_padding(key, Some(&iv), MODE::CBC, Padding::Zero)
+            .unwrap();
+        for &variant in [CtsVariant::CS1, CtsVariant::CS2, CtsVariant::CS3].iter() {
+            let mode = MODE::CTS(variant);
+            let ciphertext = cleartext[..len].encrypt(key, Some(&iv), mode).unwrap();
+            assert_eq!(len, ciphertext.len());
+            assert_eq!(
+                &cleartext[..len],
+                &ciphertext.decrypt(key, Some(&iv), mode).unwrap()[..]
+            );
+            if variant == CtsVariant::CS1 {
+                // Apart from the truncated penultimate block, CS1 agrees with CBC
+                assert_eq!(
+                    &cbc[cbc.len() - BLOCK_SIZE..],
+                    &ciphertext[len - BLOCK_SIZE..]
+                );
+                assert_eq!(&cbc[..len - BLOCK_SIZE], &ciphertext[..len - BLOCK_SIZE]);
+            }
+        }
+    }
+    assert!(cleartext[..BLOCK_SIZE - 1]
+        .encrypt(key, Some(&iv), MODE::CTS(CtsVariant::CS1))
+        .is_err());
+}
@@ -0,0 +1,144 @@
+// Galois/Counter Mode as specified in NIST SP 800-38D. Only 96-bit nonces and
+// full 128-bit tags are supported.
+//
+// Encryption is CTR mode with a 32-bit big-endian counter in the last four
+// bytes of the counter block. The tag is computed with GHASH over the
+// associated data and the ciphertext and is then encrypted with the first
+// counter block.
+
+use crate::ctr::{aes_ctr_from, CounterLayout};
+use crate::{Aes, AesError, KeySize, BLOCK_SIZE};
+use cipher::constant_time_eq;
+
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+
+pub fn encrypt_aes_gcm(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+    let j0 = initial_counter(key, nonce)?;
+    let ciphertext = gctr(input, key, &j0)?;
+    let tag = compute_tag(key, &j0, aad, &ciphertext)?;
+    Ok((ciphertext, tag))
+}
+
+pub fn decrypt_aes_gcm(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>, AesError> {
+    let j0 = initial_counter(key, nonce)?;
+    let expected_tag = compute_tag(key, &j0, aad, input)?;
+    if !constant_time_eq(&expected_tag, tag) {
+        return Err(AesError::AuthenticationFailed);
+    }
+    gctr(input, key, &j0)
+}
+
+fn initial_counter(key: &[u8], nonce: &[u8]) -> Result<Vec<u8>, AesError> {
+    KeySize::from_key_len(key.len())?;
+    if nonce.len() != NONCE_SIZE {
+        return Err(AesError::InvalidNonceLength { len: nonce.len() });
+    }
+
+    let mut j0 = nonce.to_vec();
+    j0.extend_from_slice(&[0, 0, 0, 1]);
+    Ok(j0)
+}
+
+fn gctr(input: &[u8], key: &[u8], j0: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut counter = j0.to_vec();
+    CounterLayout::BigEndian32.increment(&mut counter);
+    aes_ctr_from(input, key, &counter, CounterLayout::BigEndian32)
+}
+
+fn compute_tag(key: &[u8], j0: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    let aes = Aes::new(key)?;
+    let h = to_u128(&aes.encrypt(&[0; BLOCK_SIZE])?);
+    let s = ghash(h, aad, ciphertext);
+    let mask = to_u128(&aes.encrypt(j0)?);
+    Ok((s ^ mask).to_be_bytes().to_vec())
+}
+
+fn ghash(h: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+    let mut y = 0;
+    for block in aad.chunks(BLOCK_SIZE).chain(ciphertext.chunks(BLOCK_SIZE)) {
+        y = gf_mul(y ^ to_u128(block), h);
+    }
+    let lengths = (((aad.len() as u128) * 8) << 64) | ((ciphertext.len() as u128) * 8);
+    gf_mul(y ^ lengths, h)
+}
+
+// Multiplication in GF(2^128) using the bit order of the GCM specification,
+// that is the most significant bit of x is the coefficient of 1.
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        if (x >> i) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+// Interprets a block of at most BLOCK_SIZE bytes as a big-endian number,
+// padding it with zeros on the right.
+fn to_u128(block: &[u8]) -> u128 {
+    let mut buf = [0; BLOCK_SIZE];
+    buf[..block.len()].copy_from_slice(block);
+    u128::from_be_bytes(buf)
+}
+
+#[test]
+fn test_gcm() {
+    use serialize::from_hex;
+
+    // Test case 4 from the original GCM specification
+    let key = from_hex("feffe9928665731c6d6a8f9467308308").unwrap();
+    let nonce = from_hex("cafebabefacedbaddecaf888").unwrap();
+    let aad = from_hex("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+    let cleartext = from_hex(
+        "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+         1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+    )
+    .unwrap();
+    let ciphertext = from_hex(
+        "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+         21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+    )
+    .unwrap();
+    let tag = from_hex("5bc94fbc3221a5db94fae95ae7121a47").unwrap();
+
+    assert_eq!(
+        (ciphertext.clone(), tag.clone()),
+        encrypt_aes_gcm(&cleartext, &key, &nonce, &aad).unwrap()
+    );
+    assert_eq!(
+        cleartext,
+        decrypt_aes_gcm(&ciphertext, &key, &nonce, &aad, &tag).unwrap()
+    );
+}
+
+#[test]
+fn test_gcm_authentication_failure() {
+    let key = [0; 32];
+    let nonce = [0; NONCE_SIZE];
+    let (mut ciphertext, tag) = encrypt_aes_gcm(b"attack at dawn", &key, &nonce, b"aad").unwrap();
+    match decrypt_aes_gcm(&ciphertext, &key, &nonce, b"other aad", &tag) {
+        Err(AesError::AuthenticationFailed) => (),
+        _ => panic!("expected AuthenticationFailed"),
+    }
+    ciphertext[0] ^= 1;
+    match decrypt_aes_gcm(&ciphertext, &key, &nonce, b"aad", &tag) {
+        Err(AesError::AuthenticationFailed) => (),
+        _ => panic!("expected AuthenticationFailed"),
+    }
+}
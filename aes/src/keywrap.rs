@@ -0,0 +1,188 @@
+// AES key wrap as specified in RFC 3394 and the variant with padding from
+// RFC 5649. Both use the default initial values from the RFCs, which also
+// serve as integrity check when unwrapping.
+
+use crate::{decrypt_aes_block, encrypt_aes_block, Aes, AesError, KeySize};
+
+const SEMIBLOCK_SIZE: usize = 8;
+const DEFAULT_IV: [u8; SEMIBLOCK_SIZE] = [0xa6; SEMIBLOCK_SIZE];
+const ALTERNATIVE_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+// The key to be wrapped needs to consist of at least two 64-bit blocks.
+pub fn wrap(kek: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    if key.len() < 2 * SEMIBLOCK_SIZE || !key.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AesError::InvalidParameter);
+    }
+    wrap_with_iv(kek, key, &DEFAULT_IV)
+}
+
+pub fn unwrap(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, AesError> {
+    if wrapped.len() < 3 * SEMIBLOCK_SIZE || !wrapped.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AesError::InvalidParameter);
+    }
+
+    let (iv, key) = unwrap_with_iv(kek, wrapped)?;
+    if iv != DEFAULT_IV {
+        return Err(AesError::IntegrityCheckFailed);
+    }
+    Ok(key)
+}
+
+// Keys of arbitrary non-zero length are padded with zeros to a multiple of
+// 64 bits. The initial value records the original length.
+pub fn wrap_with_padding(kek: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    if key.is_empty() || key.len() > u32::MAX as usize {
+        return Err(AesError::InvalidParameter);
+    }
+
+    let mut iv = ALTERNATIVE_IV_PREFIX.to_vec();
+    iv.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    let mut padded = key.to_vec();
+    padded.resize(key.len().div_ceil(SEMIBLOCK_SIZE) * SEMIBLOCK_SIZE, 0);
+
+    // A single 64-bit block is encrypted together with the initial value
+    // as one AES block.
+    if padded.len() == SEMIBLOCK_SIZE {
+        KeySize::from_key_len(kek.len())?;
+        iv.extend_from_slice(&padded);
+        return encrypt_aes_block(&iv, kek);
+    }
+    wrap_with_iv(kek, &padded, &iv)
+}
+
+pub fn unwrap_with_padding(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, AesError> {
+    if wrapped.len() < 2 * SEMIBLOCK_SIZE || !wrapped.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AesError::InvalidParameter);
+    }
+
+    let (iv, mut key) = if wrapped.len() == 2 * SEMIBLOCK_SIZE {
+        KeySize::from_key_len(kek.len())?;
+        let mut block = decrypt_aes_block(wrapped, kek)?;
+        let key = block.split_off(SEMIBLOCK_SIZE);
+        (block, key)
+    } else {
+        unwrap_with_iv(kek, wrapped)?
+    };
+
+    if iv[..4] != ALTERNATIVE_IV_PREFIX {
+        return Err(AesError::IntegrityCheckFailed);
+    }
+    let len = u32::from_be_bytes([iv[4], iv[5], iv[6], iv[7]]) as usize;
+    if len > key.len() || len + SEMIBLOCK_SIZE <= key.len() || key[len..].iter().any(|&b| b != 0) {
+        return Err(AesError::IntegrityCheckFailed);
+    }
+    key.truncate(len);
+    Ok(key)
+}
+
+// The wrapping function W from RFC 3394, section 2.2.1
+fn wrap_with_iv(kek: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    let aes = Aes::new(kek)?;
+
+    let n = key.len() / SEMIBLOCK_SIZE;
+    let mut a = iv.to_vec();
+    let mut r: Vec<Vec<u8>> = key.chunks(SEMIBLOCK_SIZE).map(|c| c.to_vec()).collect();
+    for j in 0..6 {
+        for (i, r_i) in r.iter_mut().enumerate() {
+            a.extend_from_slice(r_i);
+            let mut b = aes.encrypt(&a)?;
+            *r_i = b.split_off(SEMIBLOCK_SIZE);
+            a = b;
+            xor_counter(&mut a, n * j + i + 1);
+        }
+    }
+
+    for r_i in r {
+        a.extend_from_slice(&r_i);
+    }
+    Ok(a)
+}
+
+// The unwrapping function W^-1 from RFC 3394, section 2.2.2. Returns the
+// recovered initial value along with the key.
+fn unwrap_with_iv(kek: &[u8], wrapped: &[u8]) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+    let aes = Aes::new(kek)?;
+
+    let n = wrapped.len() / SEMIBLOCK_SIZE - 1;
+    let mut a = wrapped[..SEMIBLOCK_SIZE].to_vec();
+    let mut r: Vec<Vec<u8>> = wrapped[SEMIBLOCK_SIZE..]
+        .chunks(SEMIBLOCK_SIZE)
+        .map(|c| c.to_vec())
+        .collect();
+    for j in (0..6).rev() {
+        for (i, r_i) in r.iter_mut().enumerate().rev() {
+            xor_counter(&mut a, n * j + i + 1);
+            a.extend_from_slice(r_i);
+            let mut b = aes.decrypt(&a)?;
+            *r_i = b.split_off(SEMIBLOCK_SIZE);
+            a = b;
+        }
+    }
+    Ok((a, r.concat()))
+}
+
+fn xor_counter(a: &mut [u8], t: usize) {
+    for (x, y) in a.iter_mut().zip((t as u64).to_be_bytes().iter()) {
+        *x ^= y;
+    }
+}
+
+#[test]
+fn test_key_wrap() {
+    use serialize::from_hex;
+
+    // Test vectors from RFC 3394, sections 4.1 and 4.6
+    let vectors = [
+        (
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+        ),
+        (
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f",
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326\
+             cbc7f0e71a99f43bfb988b9b7a02dd21",
+        ),
+    ];
+    for &(kek, key, wrapped) in vectors.iter() {
+        let kek = from_hex(kek).unwrap();
+        let key = from_hex(key).unwrap();
+        let mut wrapped = from_hex(wrapped).unwrap();
+        assert_eq!(wrapped, wrap(&kek, &key).unwrap());
+        assert_eq!(key, unwrap(&kek, &wrapped).unwrap());
+
+        wrapped[3] ^= 1;
+        match unwrap(&kek, &wrapped) {
+            Err(AesError::IntegrityCheckFailed) => (),
+            _ => panic!("expected IntegrityCheckFailed"),
+        }
+    }
+}
+
+#[test]
+fn test_key_wrap_with_padding() {
+    use serialize::from_hex;
+
+    // Test vectors from RFC 5649, section 6
+    let kek = from_hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+    let vectors = [
+        (
+            "c37b7e6492584340bed12207808941155068f738",
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+        ),
+        ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+    ];
+    for &(key, wrapped) in vectors.iter() {
+        let key = from_hex(key).unwrap();
+        let mut wrapped = from_hex(wrapped).unwrap();
+        assert_eq!(wrapped, wrap_with_padding(&kek, &key).unwrap());
+        assert_eq!(key, unwrap_with_padding(&kek, &wrapped).unwrap());
+
+        wrapped[0] ^= 1;
+        match unwrap_with_padding(&kek, &wrapped) {
+            Err(AesError::IntegrityCheckFailed) => (),
+            _ => panic!("expected IntegrityCheckFailed"),
+        }
+    }
+}
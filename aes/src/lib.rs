@@ -1,8 +1,32 @@
+extern crate cipher;
+#[cfg(feature = "openssl")]
 extern crate openssl;
 extern crate rand;
 extern crate xor;
 
+pub mod ccm;
+pub mod cmac;
+pub mod ctr;
+pub mod cts;
+pub mod gcm;
+pub mod keywrap;
+pub mod native;
+pub mod padding;
+pub mod parallel;
+pub mod siv;
+pub mod stream;
+pub mod xts;
+
+use cipher::BlockCipher;
+use ctr::{aes_ctr_from, CounterLayout};
+pub use cts::CtsVariant;
+use cts::{decrypt_aes_cts, encrypt_aes_cts};
+#[cfg(not(feature = "openssl"))]
+use native::NativeAes;
+#[cfg(feature = "openssl")]
 use openssl::symm::{decrypt, encrypt};
+use padding::padding_valid;
+pub use padding::{pad_inplace, unpad_inplace, unpad_inplace_constant_time, Padding};
 use rand::Rng;
 use xor::XOR;
 
@@ -11,11 +35,63 @@ use std::fmt;
 
 pub const BLOCK_SIZE: usize = 16;
 
+// The key size is not passed around explicitly. Instead it is derived from the
+// length of the key whenever a block is encrypted or decrypted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeySize {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl KeySize {
+    pub fn from_key_len(len: usize) -> Result<Self, AesError> {
+        match len {
+            16 => Ok(KeySize::Aes128),
+            24 => Ok(KeySize::Aes192),
+            32 => Ok(KeySize::Aes256),
+            _ => Err(AesError::InvalidKeyLength { len }),
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        match self {
+            KeySize::Aes128 => 16,
+            KeySize::Aes192 => 24,
+            KeySize::Aes256 => 32,
+        }
+    }
+
+    pub fn rounds(self) -> usize {
+        match self {
+            KeySize::Aes128 => 10,
+            KeySize::Aes192 => 12,
+            KeySize::Aes256 => 14,
+        }
+    }
+
+    #[cfg(feature = "openssl")]
+    fn ecb_cipher(self) -> openssl::symm::Cipher {
+        match self {
+            KeySize::Aes128 => openssl::symm::Cipher::aes_128_ecb(),
+            KeySize::Aes192 => openssl::symm::Cipher::aes_192_ecb(),
+            KeySize::Aes256 => openssl::symm::Cipher::aes_256_ecb(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum MODE {
     ECB,
     CBC,
     CTR,
+    // CFB with full-block feedback
+    CFB,
+    // CFB with 8-bit feedback
+    CFB8,
+    OFB,
+    // CBC with ciphertext stealing
+    CTS(CtsVariant),
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +105,11 @@ pub enum AesError {
     InputNotBlockSize,
     InputNotMultipleOfBlockSize,
     IvNotBlockSize,
+    InvalidKeyLength { len: usize },
+    InvalidNonceLength { len: usize },
+    AuthenticationFailed,
+    InputTooShort,
+    IntegrityCheckFailed,
 }
 
 // This is important for other errors to wrap this one.
@@ -57,56 +138,42 @@ impl fmt::Display for AesError {
                 write!(f, "input length not a multiple of {}", BLOCK_SIZE)
             }
             AesError::IvNotBlockSize => write!(f, "iv length not equal to {}", BLOCK_SIZE),
+            AesError::InvalidKeyLength { len } => {
+                write!(f, "key length {} is not one of 16, 24 or 32", len)
+            }
+            AesError::InvalidNonceLength { len } => write!(f, "invalid nonce length {}", len),
+            AesError::AuthenticationFailed => write!(f, "authentication tag mismatch"),
+            AesError::InputTooShort => write!(f, "input shorter than {}", BLOCK_SIZE),
+            AesError::IntegrityCheckFailed => write!(f, "integrity check of unwrapped key failed"),
         }
     }
 }
 
-pub fn pad_inplace(u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
-    if k < 2 {
-        return Err(AesError::InvalidParameter);
-    }
-
-    let p = k - (u.len() % k as usize) as u8;
-    for _ in 0..p {
-        u.push(p);
-    }
-    Ok(())
-}
-
-pub fn unpad_inplace(u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
-    if !padding_valid(u, k)? {
-        return Err(AesError::InvalidPadding);
-    }
-
-    let len_new = u.len() - u[u.len() - 1] as usize;
-    u.truncate(len_new);
-    Ok(())
-}
-
-fn padding_valid(u: &[u8], k: u8) -> Result<bool, AesError> {
-    if k < 2 {
-        return Err(AesError::InvalidParameter);
-    }
-
-    if u.is_empty() || u.len() % k as usize != 0 {
-        return Ok(false);
-    }
-
-    let padding = u[u.len() - 1];
-    if !(1 <= padding && padding <= k) {
-        return Ok(false);
-    }
-
-    Ok(u[u.len() - padding as usize..]
-        .iter()
-        .all(|&b| b == padding))
-}
-
+// Despite its name, this trait supports all three AES key sizes. The key size
+// is determined by the length of the key.
 pub trait Aes128 {
     fn pad(&self) -> Vec<u8>;
     fn padding_valid(&self) -> bool;
     fn encrypt(&self, key: &Self, iv: Option<&Self>, mode: MODE) -> Result<Vec<u8>, AesError>;
     fn decrypt(&self, key: &Self, iv: Option<&Self>, mode: MODE) -> Result<Vec<u8>, AesError>;
+
+    // The padding is only applied in ECB and CBC mode. The remaining modes
+    // never pad and ignore the argument. The functions `encrypt` and
+    // `decrypt` use PKCS#7.
+    fn encrypt_with_padding(
+        &self,
+        key: &Self,
+        iv: Option<&Self>,
+        mode: MODE,
+        padding: Padding,
+    ) -> Result<Vec<u8>, AesError>;
+    fn decrypt_with_padding(
+        &self,
+        key: &Self,
+        iv: Option<&Self>,
+        mode: MODE,
+        padding: Padding,
+    ) -> Result<Vec<u8>, AesError>;
 }
 
 impl Aes128 for [u8] {
@@ -121,162 +188,425 @@ impl Aes128 for [u8] {
     }
 
     fn encrypt(&self, key: &Self, iv: Option<&Self>, mode: MODE) -> Result<Vec<u8>, AesError> {
+        self.encrypt_with_padding(key, iv, mode, Padding::Pkcs7)
+    }
+
+    fn decrypt(&self, key: &Self, iv: Option<&Self>, mode: MODE) -> Result<Vec<u8>, AesError> {
+        self.decrypt_with_padding(key, iv, mode, Padding::Pkcs7)
+    }
+
+    fn encrypt_with_padding(
+        &self,
+        key: &Self,
+        iv: Option<&Self>,
+        mode: MODE,
+        padding: Padding,
+    ) -> Result<Vec<u8>, AesError> {
+        KeySize::from_key_len(key.len())?;
         match mode {
             MODE::ECB => {
                 if iv.is_some() {
                     return Err(AesError::IvNotAllowed);
                 }
-                encrypt_aes128_ecb(&self, key)
+                encrypt_aes_ecb(self, key, padding)
             }
 
             MODE::CBC => {
                 if iv.is_none() {
                     return Err(AesError::IvRequired);
                 }
-                encrypt_aes128_cbc(&self, key, iv.unwrap())
+                encrypt_aes_cbc(self, key, iv.unwrap(), padding)
             }
 
-            MODE::CTR => {
-                if iv.is_some() {
-                    return Err(AesError::IvNotAllowed);
-                }
-                aes128_ctr(&self, key)
+            // The optional iv is the initial counter block. The counter
+            // occupies the second half of the block in little-endian order.
+            MODE::CTR => aes_ctr(self, key, iv),
+
+            MODE::CFB => encrypt_aes_cfb(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::CFB8 => encrypt_aes_cfb8(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::OFB => aes_ofb(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::CTS(variant) => {
+                encrypt_aes_cts(self, key, iv.ok_or(AesError::IvRequired)?, variant)
             }
         }
     }
 
-    fn decrypt(&self, key: &Self, iv: Option<&Self>, mode: MODE) -> Result<Vec<u8>, AesError> {
+    fn decrypt_with_padding(
+        &self,
+        key: &Self,
+        iv: Option<&Self>,
+        mode: MODE,
+        padding: Padding,
+    ) -> Result<Vec<u8>, AesError> {
+        KeySize::from_key_len(key.len())?;
         match mode {
             MODE::ECB => {
                 if iv.is_some() {
                     return Err(AesError::IvNotAllowed);
                 }
-                decrypt_aes128_ecb(&self, key)
+                decrypt_aes_ecb(self, key, padding)
             }
 
             MODE::CBC => {
                 if iv.is_none() {
                     return Err(AesError::IvRequired);
                 }
-                decrypt_aes128_cbc(&self, key, iv.unwrap())
+                decrypt_aes_cbc(self, key, iv.unwrap(), padding)
             }
 
-            MODE::CTR => {
-                if iv.is_some() {
-                    return Err(AesError::IvNotAllowed);
-                }
-                aes128_ctr(&self, key)
+            MODE::CTR => aes_ctr(self, key, iv),
+
+            MODE::CFB => decrypt_aes_cfb(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::CFB8 => decrypt_aes_cfb8(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::OFB => aes_ofb(self, key, iv.ok_or(AesError::IvRequired)?),
+
+            MODE::CTS(variant) => {
+                decrypt_aes_cts(self, key, iv.ok_or(AesError::IvRequired)?, variant)
             }
         }
     }
 }
 
-fn encrypt_aes128_block(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
-    if input.len() != BLOCK_SIZE {
-        return Err(AesError::InputNotBlockSize);
+// AES with the default backend as a BlockCipher, for use with the generic
+// modes of the cipher crate. The key size is determined by the length of the
+// key.
+pub struct Aes {
+    #[cfg(feature = "openssl")]
+    key: Vec<u8>,
+    #[cfg(not(feature = "openssl"))]
+    native: NativeAes,
+}
+
+impl Aes {
+    pub fn new(key: &[u8]) -> Result<Self, AesError> {
+        KeySize::from_key_len(key.len())?;
+        Ok(Aes {
+            #[cfg(feature = "openssl")]
+            key: key.to_vec(),
+            #[cfg(not(feature = "openssl"))]
+            native: NativeAes::new(key)?,
+        })
     }
 
-    // The OpenSSL call pads the cleartext before encrypting.
-    let mut ciphertext =
-        encrypt(openssl::symm::Cipher::aes_128_ecb(), key, None, input).map_err(|_| {
-            AesError::EncryptionFailed {
+    // Single blocks are handled by OpenSSL by default. If the crate is built
+    // without the "openssl" feature, the implementation from the native module
+    // is used instead, whose key schedule is computed once in `new`.
+    #[cfg(feature = "openssl")]
+    pub(crate) fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>, AesError> {
+        if input.len() != BLOCK_SIZE {
+            return Err(AesError::InputNotBlockSize);
+        }
+
+        // The OpenSSL call pads the cleartext before encrypting.
+        let cipher = KeySize::from_key_len(self.key.len())?.ecb_cipher();
+        let mut ciphertext =
+            encrypt(cipher, &self.key, None, input).map_err(|_| AesError::EncryptionFailed {
                 block: input.to_vec(),
-            }
-        })?;
+            })?;
 
-    ciphertext.truncate(BLOCK_SIZE);
-    Ok(ciphertext)
+        ciphertext.truncate(BLOCK_SIZE);
+        Ok(ciphertext)
+    }
+
+    #[cfg(feature = "openssl")]
+    pub(crate) fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, AesError> {
+        if input.len() != BLOCK_SIZE {
+            return Err(AesError::InputNotBlockSize);
+        }
+
+        // The OpenSSL call expects a padded cleartext.
+        let cipher = KeySize::from_key_len(self.key.len())?.ecb_cipher();
+        let padding = self.encrypt(&[BLOCK_SIZE as u8; BLOCK_SIZE])?;
+        let mut u = input.to_vec();
+        u.extend_from_slice(&padding);
+        decrypt(cipher, &self.key, None, &u).map_err(|_| AesError::DecryptionFailed {
+            block: input.to_vec(),
+        })
+    }
+
+    #[cfg(not(feature = "openssl"))]
+    pub(crate) fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>, AesError> {
+        if input.len() != BLOCK_SIZE {
+            return Err(AesError::InputNotBlockSize);
+        }
+
+        let mut block = [0; BLOCK_SIZE];
+        block.copy_from_slice(input);
+        self.native.encrypt_block(&mut block);
+        Ok(block.to_vec())
+    }
+
+    #[cfg(not(feature = "openssl"))]
+    pub(crate) fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, AesError> {
+        if input.len() != BLOCK_SIZE {
+            return Err(AesError::InputNotBlockSize);
+        }
+
+        let mut block = [0; BLOCK_SIZE];
+        block.copy_from_slice(input);
+        self.native.decrypt_block(&mut block);
+        Ok(block.to_vec())
+    }
 }
 
-fn decrypt_aes128_block(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
-    if input.len() != BLOCK_SIZE {
-        return Err(AesError::InputNotBlockSize);
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
     }
 
-    // The OpenSSL call expects a padded cleartext.
-    let padding = encrypt_aes128_block(&[BLOCK_SIZE as u8; BLOCK_SIZE], key)?;
-    let mut u = input.to_vec();
-    u.extend_from_slice(&padding);
-    decrypt(openssl::symm::Cipher::aes_128_ecb(), key, None, &u).map_err(|_| {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        // unwrap is ok, BlockCipher panics on blocks of the wrong length
+        block.copy_from_slice(&self.encrypt(block).unwrap());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        // unwrap is ok, see encrypt_block
+        block.copy_from_slice(&self.decrypt(block).unwrap());
+    }
+}
+
+// One-off operations on a single block. Loops over many blocks should create
+// an Aes once instead, so that the key is not expanded for every block.
+fn encrypt_aes_block(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    Aes::new(key)?.encrypt(input)
+}
+
+fn decrypt_aes_block(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    Aes::new(key)?.decrypt(input)
+}
+
+// Encrypts a sequence of blocks independently of each other using a single
+// cipher context. The input needs to be a multiple of BLOCK_SIZE.
+#[cfg(feature = "openssl")]
+fn encrypt_aes_blocks(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    crypt_aes_blocks(input, key, openssl::symm::Mode::Encrypt).map_err(|_| {
+        AesError::EncryptionFailed {
+            block: input[..BLOCK_SIZE.min(input.len())].to_vec(),
+        }
+    })
+}
+
+#[cfg(feature = "openssl")]
+fn decrypt_aes_blocks(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    crypt_aes_blocks(input, key, openssl::symm::Mode::Decrypt).map_err(|_| {
         AesError::DecryptionFailed {
-            block: input.to_vec(),
+            block: input[..BLOCK_SIZE.min(input.len())].to_vec(),
         }
     })
 }
 
-fn encrypt_aes128_ecb(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
-    let u = input.pad();
-    let mut ciphertext = Vec::new();
-    for block in u.chunks(BLOCK_SIZE) {
-        ciphertext.extend_from_slice(&encrypt_aes128_block(block, key)?);
+#[cfg(feature = "openssl")]
+fn crypt_aes_blocks(
+    input: &[u8],
+    key: &[u8],
+    mode: openssl::symm::Mode,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(AesError::InputNotMultipleOfBlockSize.into());
     }
-    Ok(ciphertext)
+
+    let cipher = KeySize::from_key_len(key.len())?.ecb_cipher();
+    let mut crypter = openssl::symm::Crypter::new(cipher, mode, key, None)?;
+    crypter.pad(false);
+    let mut output = vec![0; input.len() + BLOCK_SIZE];
+    let mut len = crypter.update(input, &mut output)?;
+    len += crypter.finalize(&mut output[len..])?;
+    output.truncate(len);
+    Ok(output)
 }
 
-fn decrypt_aes128_ecb(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
-    if input.len() % BLOCK_SIZE != 0 {
+#[cfg(not(feature = "openssl"))]
+fn encrypt_aes_blocks(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
         return Err(AesError::InputNotMultipleOfBlockSize);
     }
 
-    let mut cleartext = Vec::new();
-    for block in input.chunks(BLOCK_SIZE) {
-        cleartext.extend_from_slice(&decrypt_aes128_block(block, key)?);
+    let aes = NativeAes::new(key)?;
+    let mut output = input.to_vec();
+    let mut block = [0; BLOCK_SIZE];
+    for chunk in output.chunks_mut(BLOCK_SIZE) {
+        block.copy_from_slice(chunk);
+        aes.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+    Ok(output)
+}
+
+#[cfg(not(feature = "openssl"))]
+fn decrypt_aes_blocks(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(AesError::InputNotMultipleOfBlockSize);
+    }
+
+    let aes = NativeAes::new(key)?;
+    let mut output = input.to_vec();
+    let mut block = [0; BLOCK_SIZE];
+    for chunk in output.chunks_mut(BLOCK_SIZE) {
+        block.copy_from_slice(chunk);
+        aes.decrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+    Ok(output)
+}
+
+fn encrypt_aes_ecb(input: &[u8], key: &[u8], padding: Padding) -> Result<Vec<u8>, AesError> {
+    let mut u = input.to_vec();
+    padding.pad(&mut u, BLOCK_SIZE as u8)?;
+    encrypt_aes_blocks(&u, key)
+}
+
+fn decrypt_aes_ecb(input: &[u8], key: &[u8], padding: Padding) -> Result<Vec<u8>, AesError> {
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(AesError::InputNotMultipleOfBlockSize);
     }
-    unpad_inplace(&mut cleartext, BLOCK_SIZE as u8)?;
+
+    let mut cleartext = decrypt_aes_blocks(input, key)?;
+    padding.unpad(&mut cleartext, BLOCK_SIZE as u8)?;
     Ok(cleartext)
 }
 
-fn encrypt_aes128_cbc(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+fn encrypt_aes_cbc(
+    input: &[u8],
+    key: &[u8],
+    iv: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, AesError> {
     if iv.len() != BLOCK_SIZE {
         return Err(AesError::IvNotBlockSize);
     }
 
-    let u = input.pad();
+    let mut u = input.to_vec();
+    padding.pad(&mut u, BLOCK_SIZE as u8)?;
+    let aes = Aes::new(key)?;
     let mut ciphertext = Vec::new();
     let mut previous = iv.to_vec();
     for block in u.chunks(BLOCK_SIZE) {
-        let current = encrypt_aes128_block(&block.xor(&previous), key)?;
+        let current = aes.encrypt(&block.xor(&previous))?;
         ciphertext.extend_from_slice(&current);
         previous = current;
     }
     Ok(ciphertext)
 }
 
-fn decrypt_aes128_cbc(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
-    if input.len() % BLOCK_SIZE != 0 {
+fn decrypt_aes_cbc(
+    input: &[u8],
+    key: &[u8],
+    iv: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, AesError> {
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
         return Err(AesError::InputNotMultipleOfBlockSize);
     }
     if iv.len() != BLOCK_SIZE {
         return Err(AesError::IvNotBlockSize);
     }
 
+    let aes = Aes::new(key)?;
     let mut cleartext = Vec::new();
     let mut previous = iv;
     for block in input.chunks(BLOCK_SIZE) {
-        cleartext.extend_from_slice(&decrypt_aes128_block(block, key)?.xor(previous));
+        cleartext.extend_from_slice(&aes.decrypt(block)?.xor(previous));
         previous = block;
     }
-    unpad_inplace(&mut cleartext, BLOCK_SIZE as u8)?;
+    padding.unpad(&mut cleartext, BLOCK_SIZE as u8)?;
     Ok(cleartext)
 }
 
-fn aes128_ctr(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
-    let mut ciphertext = Vec::new();
-    let mut keystream = vec![0; BLOCK_SIZE];
-    for b in input.chunks(BLOCK_SIZE) {
-        ciphertext.extend_from_slice(&b.xor(&encrypt_aes128_ecb(&keystream, key)?));
-        increment_counter(&mut keystream[BLOCK_SIZE / 2..]);
+fn aes_ctr(input: &[u8], key: &[u8], iv: Option<&[u8]>) -> Result<Vec<u8>, AesError> {
+    let counter = match iv {
+        Some(iv) if iv.len() != BLOCK_SIZE => return Err(AesError::IvNotBlockSize),
+        Some(iv) => iv,
+        None => &[0; BLOCK_SIZE],
+    };
+    aes_ctr_from(input, key, counter, CounterLayout::LittleEndian64)
+}
+
+// CFB, CFB-8 and OFB turn AES into a stream cipher, so the cleartext is not
+// padded and the last block of the ciphertext may be shorter than BLOCK_SIZE.
+fn encrypt_aes_cfb(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(AesError::IvNotBlockSize);
+    }
+
+    let aes = Aes::new(key)?;
+    let mut ciphertext = Vec::with_capacity(input.len());
+    let mut previous = iv.to_vec();
+    for block in input.chunks(BLOCK_SIZE) {
+        previous = block.xor_truncating(&aes.encrypt(&previous)?);
+        ciphertext.extend_from_slice(&previous);
     }
     Ok(ciphertext)
 }
 
-fn increment_counter(v: &mut [u8]) {
-    for b in v.iter_mut() {
-        *b += 1;
-        if *b != 0 {
-            break;
-        }
+fn decrypt_aes_cfb(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(AesError::IvNotBlockSize);
     }
+
+    let aes = Aes::new(key)?;
+    let mut cleartext = Vec::with_capacity(input.len());
+    let mut previous = iv;
+    for block in input.chunks(BLOCK_SIZE) {
+        cleartext.extend_from_slice(&block.xor_truncating(&aes.encrypt(previous)?));
+        previous = block;
+    }
+    Ok(cleartext)
+}
+
+// The shift register is encrypted once per byte and its first byte is used
+// as keystream. Afterwards the ciphertext byte is shifted in from the right.
+fn encrypt_aes_cfb8(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(AesError::IvNotBlockSize);
+    }
+
+    let aes = Aes::new(key)?;
+    let mut ciphertext = Vec::with_capacity(input.len());
+    let mut register = iv.to_vec();
+    for &u in input {
+        let c = u ^ aes.encrypt(&register)?[0];
+        register.remove(0);
+        register.push(c);
+        ciphertext.push(c);
+    }
+    Ok(ciphertext)
+}
+
+fn decrypt_aes_cfb8(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(AesError::IvNotBlockSize);
+    }
+
+    let aes = Aes::new(key)?;
+    let mut cleartext = Vec::with_capacity(input.len());
+    let mut register = iv.to_vec();
+    for &c in input {
+        cleartext.push(c ^ aes.encrypt(&register)?[0]);
+        register.remove(0);
+        register.push(c);
+    }
+    Ok(cleartext)
+}
+
+// Encryption and decryption coincide in OFB mode.
+fn aes_ofb(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, AesError> {
+    if iv.len() != BLOCK_SIZE {
+        return Err(AesError::IvNotBlockSize);
+    }
+
+    let aes = Aes::new(key)?;
+    let mut ciphertext = Vec::with_capacity(input.len());
+    let mut keystream = iv.to_vec();
+    for block in input.chunks(BLOCK_SIZE) {
+        keystream = aes.encrypt(&keystream)?;
+        ciphertext.extend_from_slice(&block.xor_truncating(&keystream));
+    }
+    Ok(ciphertext)
 }
 
 pub fn random_block() -> Vec<u8> {
@@ -284,13 +614,18 @@ pub fn random_block() -> Vec<u8> {
     rng.gen_iter().take(BLOCK_SIZE).collect()
 }
 
+pub fn random_key(size: KeySize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    rng.gen_iter().take(size.key_len()).collect()
+}
+
 // Returns a pair (chunks_count, fill_len), where chunks_count is the number of
 // chunks resulting from dividing a message of length len into chunks of size
 // BLOCK_SIZE. This count explicitly includes the last chunk, which may
 // be shorter than BLOCK_SIZE. The number fill_len is the difference between
 // chunks_count * BLOCK_SIZE and len.
 pub fn chunks_count(len: usize) -> (usize, usize) {
-    let q = (len + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    let q = len.div_ceil(BLOCK_SIZE);
     let r = q * BLOCK_SIZE - len;
     (q, r)
 }
@@ -311,17 +646,148 @@ fn aes_128_cbc() {
 }
 
 #[test]
-fn test_padding_valid() {
-    assert!(padding_valid("ICE ICE BABY\x04\x04\x04\x04".as_bytes(), 16).unwrap() == true);
-    assert!(padding_valid("ICE ICE BABY\x05\x05\x05\x05".as_bytes(), 16).unwrap() == false);
-    assert!(padding_valid("ICE ICE BABY\x03\x03\x03".as_bytes(), 16).unwrap() == false);
-    assert!(padding_valid("ICE ICE BABY\x01\x02\x03\x04".as_bytes(), 16).unwrap() == false);
-    assert!(
-        padding_valid(
-            "ICE ICE BABY\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C".as_bytes(),
-            12
-        )
-        .unwrap()
-            == true
+fn aes_192_and_256_block() {
+    // Test vectors from FIPS-197, Appendix C.2 and C.3
+    let input = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    let key: Vec<u8> = (0..32).collect();
+    let expected_192 = [
+        0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d, 0x71,
+        0x91,
+    ];
+    let expected_256 = [
+        0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49, 0x60,
+        0x89,
+    ];
+    assert_eq!(
+        &expected_192[..],
+        &encrypt_aes_block(&input, &key[..24]).unwrap()[..]
+    );
+    assert_eq!(
+        &expected_256[..],
+        &encrypt_aes_block(&input, &key).unwrap()[..]
+    );
+    assert_eq!(
+        &input[..],
+        &decrypt_aes_block(&expected_256, &key).unwrap()[..]
+    );
+
+    let iv = [0; BLOCK_SIZE];
+    assert_eq!(
+        input.as_ref(),
+        &input
+            .encrypt(&key, Some(&iv), MODE::CBC)
+            .unwrap()
+            .decrypt(&key, Some(&iv), MODE::CBC)
+            .unwrap()[..]
+    );
+}
+
+#[test]
+fn aes_block_cipher() {
+    let key = b"YELLOW SUBMARINE";
+    let iv = [7; BLOCK_SIZE];
+    let input = b"ABCDEFGHIJKLMNOPQRSTU";
+    let aes = Aes::new(key).unwrap();
+    assert_eq!(
+        input.encrypt(key, None, MODE::ECB).unwrap(),
+        cipher::encrypt_ecb(&aes, input).unwrap()
+    );
+    let ciphertext = cipher::encrypt_cbc(&aes, input, &iv).unwrap();
+    assert_eq!(
+        input.encrypt(key, Some(&iv), MODE::CBC).unwrap(),
+        ciphertext
+    );
+    assert_eq!(
+        input.as_ref(),
+        &cipher::decrypt_cbc(&aes, &ciphertext, &iv).unwrap()[..]
+    );
+    assert!(Aes::new(&[0; 20]).is_err());
+}
+
+#[test]
+fn invalid_key_length() {
+    match b"ABCDEFGHIJKLMNOP".encrypt(&[0; 20], None, MODE::CTR) {
+        Err(AesError::InvalidKeyLength { len: 20 }) => (),
+        _ => panic!("expected InvalidKeyLength"),
+    }
+}
+
+#[test]
+fn aes_cfb_and_ofb() {
+    use serialize::from_hex;
+
+    // Test vectors F.3.1, F.3.7 and F.4.1 from NIST SP 800-38A, truncated to 29 bytes
+    let key = from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+    let iv: Vec<u8> = (0..16).collect();
+    let cleartext = from_hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45").unwrap();
+    let expected = [
+        (
+            MODE::CFB,
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f",
+        ),
+        (
+            MODE::CFB8,
+            "3b79424c9c0dd436bace9e0ed4586a4f32b9ded50ae3ba69d472e88267",
+        ),
+        (
+            MODE::OFB,
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac5",
+        ),
+    ];
+    for &(mode, ciphertext) in expected.iter() {
+        let ciphertext = from_hex(ciphertext).unwrap();
+        assert_eq!(
+            ciphertext,
+            cleartext.encrypt(&key, Some(&iv), mode).unwrap()
+        );
+        assert_eq!(
+            cleartext,
+            ciphertext.decrypt(&key, Some(&iv), mode).unwrap()
+        );
+    }
+}
+
+#[test]
+fn aes_cbc_with_padding() {
+    let iv = [0; BLOCK_SIZE];
+    let key = b"YELLOW SUBMARINE";
+    let input = b"ABCDEFGHIJKLMNOP";
+
+    let ciphertext = input
+        .encrypt_with_padding(key, Some(&iv), MODE::CBC, Padding::NoPadding)
+        .unwrap();
+    assert_eq!(BLOCK_SIZE, ciphertext.len());
+    assert_eq!(
+        input.as_ref(),
+        &ciphertext
+            .decrypt_with_padding(key, Some(&iv), MODE::CBC, Padding::NoPadding)
+            .unwrap()[..]
+    );
+
+    let ciphertext = input[..10]
+        .encrypt_with_padding(key, None, MODE::ECB, Padding::Iso7816)
+        .unwrap();
+    assert_eq!(
+        &input[..10],
+        &ciphertext
+            .decrypt_with_padding(key, None, MODE::ECB, Padding::Iso7816)
+            .unwrap()[..]
+    );
+    assert!(ciphertext
+        .decrypt_with_padding(key, None, MODE::ECB, Padding::AnsiX923)
+        .is_err());
+
+    let ciphertext = input[..10].encrypt(key, Some(&iv), MODE::CBC).unwrap();
+    assert_eq!(
+        &input[..10],
+        &ciphertext
+            .decrypt_with_padding(key, Some(&iv), MODE::CBC, Padding::Pkcs7ConstantTime)
+            .unwrap()[..]
     );
+    assert!(ciphertext
+        .decrypt_with_padding(key, Some(&iv), MODE::CBC, Padding::Iso7816)
+        .is_err());
 }
@@ -0,0 +1,342 @@
+// A pure Rust implementation of the AES block cipher. In contrast to the
+// OpenSSL backend, the individual round functions and the key schedule are
+// exposed, and the number of rounds can be chosen freely. This makes it
+// possible to experiment with reduced-round variants.
+//
+// The state is stored as 16 bytes in the order in which they appear in the
+// input block, that is byte i is found in row i % 4 and column i / 4.
+
+use crate::{AesError, KeySize, BLOCK_SIZE};
+use cipher::BlockCipher;
+
+pub type State = [u8; BLOCK_SIZE];
+
+static SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+static INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+pub fn sub_bytes(state: &mut State) {
+    for b in state.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+pub fn inv_sub_bytes(state: &mut State) {
+    for b in state.iter_mut() {
+        *b = INV_SBOX[*b as usize];
+    }
+}
+
+// Row r is rotated to the left by r positions.
+pub fn shift_rows(state: &mut State) {
+    let old = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * c] = old[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut State) {
+    let old = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = old[r + 4 * c];
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut State) {
+    for column in state.chunks_mut(4) {
+        let (a0, a1, a2, a3) = (column[0], column[1], column[2], column[3]);
+        column[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        column[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+pub fn inv_mix_columns(state: &mut State) {
+    for column in state.chunks_mut(4) {
+        let (a0, a1, a2, a3) = (column[0], column[1], column[2], column[3]);
+        column[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        column[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        column[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        column[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+pub fn add_round_key(state: &mut State, round_key: &State) {
+    for (s, k) in state.iter_mut().zip(round_key.iter()) {
+        *s ^= k;
+    }
+}
+
+// Returns rounds + 1 round keys. The schedule is the one from FIPS-197, it is
+// simply continued (or cut off) if the number of rounds differs from the
+// standard one for the given key size.
+pub fn expand_key(key: &[u8], rounds: usize) -> Result<Vec<State>, AesError> {
+    let nk = KeySize::from_key_len(key.len())?.key_len() / 4;
+    if rounds == 0 {
+        return Err(AesError::InvalidParameter);
+    }
+
+    let words_count = 4 * (rounds + 1);
+    let mut words: Vec<[u8; 4]> = key.chunks(4).map(|w| [w[0], w[1], w[2], w[3]]).collect();
+    let mut rcon = 1u8;
+    for i in nk..words_count {
+        let mut temp = words[i - 1];
+        if i % nk == 0 {
+            temp.rotate_left(1);
+            sub_word(&mut temp);
+            temp[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if nk > 6 && i % nk == 4 {
+            sub_word(&mut temp);
+        }
+        let previous = words[i - nk];
+        words.push([
+            previous[0] ^ temp[0],
+            previous[1] ^ temp[1],
+            previous[2] ^ temp[2],
+            previous[3] ^ temp[3],
+        ]);
+    }
+    words.truncate(words_count);
+
+    Ok(words
+        .chunks(4)
+        .map(|w| {
+            let mut round_key = [0; BLOCK_SIZE];
+            for (dst, src) in round_key.chunks_mut(4).zip(w.iter()) {
+                dst.copy_from_slice(src);
+            }
+            round_key
+        })
+        .collect())
+}
+
+fn sub_word(word: &mut [u8; 4]) {
+    for b in word.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    result
+}
+
+pub struct NativeAes {
+    round_keys: Vec<State>,
+}
+
+impl NativeAes {
+    pub fn new(key: &[u8]) -> Result<Self, AesError> {
+        Self::with_rounds(key, KeySize::from_key_len(key.len())?.rounds())
+    }
+
+    pub fn with_rounds(key: &[u8], rounds: usize) -> Result<Self, AesError> {
+        Ok(NativeAes {
+            round_keys: expand_key(key, rounds)?,
+        })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn round_keys(&self) -> &[State] {
+        &self.round_keys
+    }
+
+    // As in the standard, the last round omits MixColumns.
+    pub fn encrypt_block(&self, block: &mut State) {
+        let rounds = self.rounds();
+        add_round_key(block, &self.round_keys[0]);
+        for round_key in &self.round_keys[1..rounds] {
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, round_key);
+        }
+        sub_bytes(block);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[rounds]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut State) {
+        let rounds = self.rounds();
+        add_round_key(block, &self.round_keys[rounds]);
+        inv_shift_rows(block);
+        inv_sub_bytes(block);
+        for round_key in self.round_keys[1..rounds].iter().rev() {
+            add_round_key(block, round_key);
+            inv_mix_columns(block);
+            inv_shift_rows(block);
+            inv_sub_bytes(block);
+        }
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+impl BlockCipher for NativeAes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let mut state = [0; BLOCK_SIZE];
+        state.copy_from_slice(block);
+        NativeAes::encrypt_block(self, &mut state);
+        block.copy_from_slice(&state);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let mut state = [0; BLOCK_SIZE];
+        state.copy_from_slice(block);
+        NativeAes::decrypt_block(self, &mut state);
+        block.copy_from_slice(&state);
+    }
+}
+
+#[test]
+fn test_expand_key() {
+    // Test vector from FIPS-197, Appendix A.1
+    let key = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    let last_round_key = [
+        0xd0, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89, 0xe1, 0x3f, 0x0c, 0xc8, 0xb6, 0x63, 0x0c,
+        0xa6,
+    ];
+    let round_keys = expand_key(&key, 10).unwrap();
+    assert_eq!(11, round_keys.len());
+    assert_eq!(key, round_keys[0]);
+    assert_eq!(last_round_key, round_keys[10]);
+}
+
+#[test]
+fn test_native_aes() {
+    // Test vectors from FIPS-197, Appendix C
+    let input = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    let key: Vec<u8> = (0..32).collect();
+    let expected = [
+        (
+            16,
+            [
+                0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4,
+                0xc5, 0x5a,
+            ],
+        ),
+        (
+            24,
+            [
+                0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d,
+                0x71, 0x91,
+            ],
+        ),
+        (
+            32,
+            [
+                0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49,
+                0x60, 0x89,
+            ],
+        ),
+    ];
+    for &(key_len, ciphertext) in expected.iter() {
+        let aes = NativeAes::new(&key[..key_len]).unwrap();
+        let mut block = input;
+        aes.encrypt_block(&mut block);
+        assert_eq!(ciphertext, block);
+        aes.decrypt_block(&mut block);
+        assert_eq!(input, block);
+    }
+}
+
+#[test]
+fn test_reduced_rounds() {
+    let key = [0; 16];
+    assert!(NativeAes::with_rounds(&key, 0).is_err());
+    for rounds in 1..=16 {
+        let aes = NativeAes::with_rounds(&key, rounds).unwrap();
+        let mut block = *b"YELLOW SUBMARINE";
+        aes.encrypt_block(&mut block);
+        assert_ne!(b"YELLOW SUBMARINE", &block);
+        aes.decrypt_block(&mut block);
+        assert_eq!(b"YELLOW SUBMARINE", &block);
+    }
+}
+
+#[test]
+fn test_generic_modes() {
+    use crate::{Aes128, MODE};
+    use cipher::{Ctr, StreamCipher};
+
+    let key = b"YELLOW SUBMARINE";
+    let iv = [3; BLOCK_SIZE];
+    let input = b"Ladies and Gentlemen of the class of '99";
+    let aes = NativeAes::new(key).unwrap();
+    assert_eq!(
+        input.encrypt(key, None, MODE::ECB).unwrap(),
+        cipher::encrypt_ecb(&aes, input).unwrap()
+    );
+    assert_eq!(
+        input.encrypt(key, Some(&iv), MODE::CBC).unwrap(),
+        cipher::encrypt_cbc(&aes, input, &iv).unwrap()
+    );
+    assert_eq!(
+        input.encrypt(key, None, MODE::CTR).unwrap(),
+        Ctr::new(&aes, &[0; 8]).unwrap().process(input)
+    );
+}
@@ -0,0 +1,336 @@
+// Padding schemes for the block cipher modes. PKCS#7 is the default and is
+// also available through the free functions `pad_inplace` and
+// `unpad_inplace`. The other schemes are selected through `Padding`.
+
+use crate::AesError;
+use cipher::{pad_pkcs7, pkcs7_padding_len};
+use rand::Rng;
+
+use std::hint;
+
+pub fn pad_inplace(u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
+    if k < 2 {
+        return Err(AesError::InvalidParameter);
+    }
+
+    pad_pkcs7(u, k as usize).map_err(|_| AesError::InvalidParameter)
+}
+
+pub fn unpad_inplace(u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
+    if !padding_valid(u, k)? {
+        return Err(AesError::InvalidPadding);
+    }
+
+    let len_new = u.len() - u[u.len() - 1] as usize;
+    u.truncate(len_new);
+    Ok(())
+}
+
+// Same as unpad_inplace, but the running time only depends on the length of
+// u and not on its contents, so that it does not act as a padding oracle.
+pub fn unpad_inplace_constant_time(u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
+    if k < 2 {
+        return Err(AesError::InvalidParameter);
+    }
+
+    if u.is_empty() || !u.len().is_multiple_of(k as usize) {
+        return Err(AesError::InvalidPadding);
+    }
+
+    let len = pkcs7_padding_len_constant_time(u, k);
+    if len == 0 {
+        return Err(AesError::InvalidPadding);
+    }
+
+    u.truncate(u.len() - len);
+    Ok(())
+}
+
+// Returns the PKCS#7 padding length, or 0 if the padding is invalid. The last
+// k bytes are always inspected and the checks are combined into a mask
+// without branching on secret data. u needs to be a non-empty multiple of k.
+fn pkcs7_padding_len_constant_time(u: &[u8], k: u8) -> usize {
+    let padding = u32::from(u[u.len() - 1]);
+    let mut good = !is_zero_mask(padding) & less_than_mask(padding, u32::from(k) + 1);
+    for (i, &b) in u[u.len() - k as usize..].iter().rev().enumerate() {
+        let in_padding = less_than_mask(i as u32, padding);
+        good &= !in_padding | is_zero_mask(u32::from(b) ^ padding);
+    }
+    hint::black_box(padding & good) as usize
+}
+
+// All ones if x == 0, zero otherwise.
+fn is_zero_mask(x: u32) -> u32 {
+    ((x | x.wrapping_neg()) >> 31).wrapping_sub(1)
+}
+
+// All ones if a < b, zero otherwise. Both need to be less than 2^31.
+fn less_than_mask(a: u32, b: u32) -> u32 {
+    (a.wrapping_sub(b) >> 31).wrapping_neg()
+}
+
+pub(crate) fn padding_valid(u: &[u8], k: u8) -> Result<bool, AesError> {
+    if k < 2 {
+        return Err(AesError::InvalidParameter);
+    }
+
+    Ok(pkcs7_padding_len(u, k as usize).is_some())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Padding {
+    Pkcs7,
+    // PKCS#7, validated and removed in constant time
+    Pkcs7ConstantTime,
+    // Zeros followed by the padding length
+    AnsiX923,
+    // 0x80 followed by zeros
+    Iso7816,
+    // Random bytes followed by the padding length
+    Iso10126,
+    // Zeros, but only if the input is not already a multiple of the block
+    // size. Trailing zeros of the cleartext are lost when unpadding.
+    Zero,
+    // The input needs to be a multiple of the block size.
+    NoPadding,
+}
+
+impl Padding {
+    pub fn pad(self, u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
+        if k < 2 {
+            return Err(AesError::InvalidParameter);
+        }
+
+        let p = k as usize - u.len() % k as usize;
+        match self {
+            Padding::Pkcs7 | Padding::Pkcs7ConstantTime => return pad_inplace(u, k),
+            Padding::AnsiX923 => {
+                u.resize(u.len() + p - 1, 0);
+                u.push(p as u8);
+            }
+            Padding::Iso7816 => {
+                u.push(0x80);
+                u.resize(u.len() + p - 1, 0);
+            }
+            Padding::Iso10126 => {
+                let mut rng = rand::thread_rng();
+                u.extend(rng.gen_iter::<u8>().take(p - 1));
+                u.push(p as u8);
+            }
+            Padding::Zero => {
+                if p != k as usize {
+                    u.resize(u.len() + p, 0);
+                }
+            }
+            Padding::NoPadding => {
+                if p != k as usize {
+                    return Err(AesError::InputNotMultipleOfBlockSize);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unpad(self, u: &mut Vec<u8>, k: u8) -> Result<(), AesError> {
+        match self.padding_len(u, k)? {
+            Some(len) => {
+                u.truncate(u.len() - len);
+                Ok(())
+            }
+            None => Err(AesError::InvalidPadding),
+        }
+    }
+
+    pub fn is_valid(self, u: &[u8], k: u8) -> Result<bool, AesError> {
+        Ok(self.padding_len(u, k)?.is_some())
+    }
+
+    // Returns the number of padding bytes at the end of u, or None if the
+    // padding is not valid for the scheme.
+    fn padding_len(self, u: &[u8], k: u8) -> Result<Option<usize>, AesError> {
+        if k < 2 {
+            return Err(AesError::InvalidParameter);
+        }
+
+        if !u.len().is_multiple_of(k as usize) {
+            return Ok(None);
+        }
+
+        Ok(match self {
+            Padding::Pkcs7 => pkcs7_padding_len(u, k as usize),
+            Padding::Pkcs7ConstantTime if u.is_empty() => None,
+            Padding::Pkcs7ConstantTime => match pkcs7_padding_len_constant_time(u, k) {
+                0 => None,
+                len => Some(len),
+            },
+            Padding::AnsiX923 => ansi_x923_padding_len(u, k),
+            Padding::Iso7816 => iso_7816_padding_len(u, k),
+            Padding::Iso10126 => iso_10126_padding_len(u, k),
+            Padding::Zero => Some(trailing_zeros_count(u, k as usize - 1)),
+            Padding::NoPadding => Some(0),
+        })
+    }
+}
+
+fn ansi_x923_padding_len(u: &[u8], k: u8) -> Option<usize> {
+    let p = iso_10126_padding_len(u, k)?;
+    if u[u.len() - p..u.len() - 1].iter().all(|&b| b == 0) {
+        Some(p)
+    } else {
+        None
+    }
+}
+
+fn iso_7816_padding_len(u: &[u8], k: u8) -> Option<usize> {
+    let zeros = trailing_zeros_count(u, k as usize - 1);
+    if zeros < u.len() && u[u.len() - zeros - 1] == 0x80 {
+        Some(zeros + 1)
+    } else {
+        None
+    }
+}
+
+// Only the length byte can be checked, the remaining padding is random.
+fn iso_10126_padding_len(u: &[u8], k: u8) -> Option<usize> {
+    let p = *u.last()?;
+    if 1 <= p && p <= k {
+        Some(p as usize)
+    } else {
+        None
+    }
+}
+
+fn trailing_zeros_count(u: &[u8], max: usize) -> usize {
+    u.iter().rev().take(max).take_while(|&&b| b == 0).count()
+}
+
+#[test]
+fn test_padding_valid() {
+    assert!(padding_valid("ICE ICE BABY\x04\x04\x04\x04".as_bytes(), 16).unwrap());
+    assert!(!padding_valid("ICE ICE BABY\x05\x05\x05\x05".as_bytes(), 16).unwrap());
+    assert!(!padding_valid("ICE ICE BABY\x03\x03\x03".as_bytes(), 16).unwrap());
+    assert!(!padding_valid("ICE ICE BABY\x01\x02\x03\x04".as_bytes(), 16).unwrap());
+    assert!(padding_valid(
+        "ICE ICE BABY\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C\x0C".as_bytes(),
+        12
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_padding_schemes() {
+    let schemes = [
+        Padding::Pkcs7,
+        Padding::Pkcs7ConstantTime,
+        Padding::AnsiX923,
+        Padding::Iso7816,
+        Padding::Iso10126,
+    ];
+    for &scheme in schemes.iter() {
+        for len in 0..=32 {
+            let original: Vec<u8> = (1..=len).collect();
+            let mut u = original.clone();
+            scheme.pad(&mut u, 16).unwrap();
+            assert!(u.len() > original.len() && u.len().is_multiple_of(16));
+            assert!(scheme.is_valid(&u, 16).unwrap());
+            scheme.unpad(&mut u, 16).unwrap();
+            assert_eq!(original, u);
+        }
+    }
+
+    assert!(Padding::AnsiX923
+        .is_valid(b"ICE ICE BABY\x00\x00\x00\x04", 16)
+        .unwrap());
+    assert!(!Padding::AnsiX923
+        .is_valid(b"ICE ICE BABY\x00\x01\x00\x04", 16)
+        .unwrap());
+    assert!(Padding::Iso7816
+        .is_valid(b"ICE ICE BABY\x80\x00\x00\x00", 16)
+        .unwrap());
+    assert!(!Padding::Iso7816
+        .is_valid(b"ICE ICE BABY\x81\x00\x00\x00", 16)
+        .unwrap());
+    assert!(!Padding::Iso10126
+        .is_valid(b"ICE ICE BABY\x00\x00\x00\x11", 16)
+        .unwrap());
+
+    let mut u = b"ICE ICE BABY".to_vec();
+    Padding::Zero.pad(&mut u, 16).unwrap();
+    assert_eq!(b"ICE ICE BABY\x00\x00\x00\x00", &u[..]);
+    Padding::Zero.unpad(&mut u, 16).unwrap();
+    assert_eq!(b"ICE ICE BABY", &u[..]);
+
+    assert!(Padding::NoPadding.pad(&mut u, 16).is_err());
+    u.extend_from_slice(b"1234");
+    Padding::NoPadding.pad(&mut u, 16).unwrap();
+    assert_eq!(16, u.len());
+}
+
+#[test]
+fn test_unpad_constant_time() {
+    let inputs: Vec<&[u8]> = vec![
+        b"ICE ICE BABY\x04\x04\x04\x04",
+        b"ICE ICE BABY ICE",
+        b"ICE ICE BABY\x01\x02\x03\x04",
+        b"ICE ICE BABY\x05\x05\x05\x05",
+        b"ICE ICE BABY\x04\x04\x04\x00",
+        b"\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10",
+        b"\x11\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10\x10",
+    ];
+    for &input in inputs.iter() {
+        let mut u = input.to_vec();
+        assert_eq!(
+            padding_valid(input, 16).unwrap(),
+            unpad_inplace_constant_time(&mut u, 16).is_ok()
+        );
+    }
+
+    // Every value of the last byte, once with a block which is valid padding
+    // for it and once with a mismatch right before the last byte.
+    for last in 0..=255u8 {
+        for &mismatch in &[0, 1] {
+            let mut u = vec![0x5a; 16];
+            u.extend(std::iter::repeat_n(last, 16));
+            u[30] ^= mismatch;
+            let expected = if padding_valid(&u, 16).unwrap() {
+                last as usize
+            } else {
+                0
+            };
+            assert_eq!(expected, pkcs7_padding_len_constant_time(&u, 16));
+        }
+    }
+}
+
+#[test]
+fn test_unpad_constant_time_timing() {
+    use std::time::{Duration, Instant};
+
+    let valid = [0x10; 16];
+    let mut invalid = [0x10; 16];
+    invalid[15] = 0x11;
+
+    let time = |u: &[u8]| -> Duration {
+        let start = Instant::now();
+        for _ in 0..1000 {
+            hint::black_box(pkcs7_padding_len_constant_time(hint::black_box(u), 16));
+        }
+        start.elapsed()
+    };
+
+    // Take the fastest of many interleaved rounds to filter out noise from the
+    // scheduler. The bound is loose so that the test holds on shared machines,
+    // but an early exit on the invalid last byte would skip almost all of the
+    // work and still show up.
+    let (mut fastest_valid, mut fastest_invalid) = (Duration::MAX, Duration::MAX);
+    for _ in 0..500 {
+        fastest_valid = fastest_valid.min(time(&valid));
+        fastest_invalid = fastest_invalid.min(time(&invalid));
+    }
+    let (fastest, slowest) = if fastest_valid < fastest_invalid {
+        (fastest_valid, fastest_invalid)
+    } else {
+        (fastest_invalid, fastest_valid)
+    };
+    assert!(slowest < 2 * fastest);
+}
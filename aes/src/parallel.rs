@@ -0,0 +1,103 @@
+// Bulk ECB and CTR encryption for large inputs. Blocks are independent in
+// both modes, so the input is split at block boundaries into chunks which are
+// processed on separate threads, with a single cipher call per chunk. The
+// output is identical to the one of the corresponding MODE.
+
+use crate::ctr::{aes_ctr_from, CounterLayout};
+use crate::{decrypt_aes_blocks, encrypt_aes_blocks, AesError, KeySize, Padding, BLOCK_SIZE};
+
+use std::panic;
+use std::thread;
+
+// Inputs are not split into chunks smaller than this, as the cost of
+// spawning a thread would outweigh the gain.
+const MIN_CHUNK_SIZE: usize = 1 << 16;
+
+pub fn encrypt_aes_ecb_parallel(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    KeySize::from_key_len(key.len())?;
+    let mut u = input.to_vec();
+    Padding::Pkcs7.pad(&mut u, BLOCK_SIZE as u8)?;
+    map_chunks_parallel(&u, |_, chunk| encrypt_aes_blocks(chunk, key))
+}
+
+pub fn decrypt_aes_ecb_parallel(input: &[u8], key: &[u8]) -> Result<Vec<u8>, AesError> {
+    KeySize::from_key_len(key.len())?;
+    if !input.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(AesError::InputNotMultipleOfBlockSize);
+    }
+
+    let mut cleartext = map_chunks_parallel(input, |_, chunk| decrypt_aes_blocks(chunk, key))?;
+    Padding::Pkcs7.unpad(&mut cleartext, BLOCK_SIZE as u8)?;
+    Ok(cleartext)
+}
+
+// Same as aes_ctr_with_nonce. Each chunk starts with the counter block
+// advanced by the number of blocks preceding it.
+pub fn aes_ctr_parallel(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    initial_counter: u128,
+    layout: CounterLayout,
+) -> Result<Vec<u8>, AesError> {
+    KeySize::from_key_len(key.len())?;
+    let counter = layout.counter_block(nonce, initial_counter)?;
+    map_chunks_parallel(input, |offset, chunk| {
+        let mut chunk_counter = counter.clone();
+        layout.add(&mut chunk_counter, (offset / BLOCK_SIZE) as u128);
+        aes_ctr_from(chunk, key, &chunk_counter, layout)
+    })
+}
+
+// Applies f to consecutive chunks of the input on separate threads and
+// concatenates the results in order. f receives the offset of the chunk in
+// the input. All chunks but the last one are a multiple of BLOCK_SIZE.
+fn map_chunks_parallel<F>(input: &[u8], f: F) -> Result<Vec<u8>, AesError>
+where
+    F: Fn(usize, &[u8]) -> Result<Vec<u8>, AesError> + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let blocks_count = input.len().div_ceil(BLOCK_SIZE);
+    let chunk_size = (blocks_count.div_ceil(threads) * BLOCK_SIZE).max(MIN_CHUNK_SIZE);
+    if input.len() <= chunk_size {
+        return f(0, input);
+    }
+
+    let results = thread::scope(|s| {
+        let f = &f;
+        let handles: Vec<_> = input
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| s.spawn(move || f(i * chunk_size, chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+
+    let mut output = Vec::with_capacity(input.len());
+    for result in results {
+        output.extend_from_slice(&result?);
+    }
+    Ok(output)
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    use crate::{random_key, Aes128, MODE};
+
+    let key = random_key(KeySize::Aes128);
+    let input: Vec<u8> = (0..(1 << 20) + 7).map(|i| (i * 31 % 251) as u8).collect();
+
+    let ciphertext = encrypt_aes_ecb_parallel(&input, &key).unwrap();
+    assert_eq!(input.encrypt(&key, None, MODE::ECB).unwrap(), ciphertext);
+    assert_eq!(input, decrypt_aes_ecb_parallel(&ciphertext, &key).unwrap());
+
+    let nonce = [7; 8];
+    let layout = CounterLayout::LittleEndian64;
+    assert_eq!(
+        crate::ctr::aes_ctr_with_nonce(&input, &key, &nonce, 3, layout).unwrap(),
+        aes_ctr_parallel(&input, &key, &nonce, 3, layout).unwrap()
+    );
+}
@@ -0,0 +1,134 @@
+// Synthetic initialization vector mode as specified in RFC 5297.
+//
+// The key is twice as long as an AES key. Its first half is used for the
+// S2V construction, which derives the synthetic iv from the associated data
+// and the cleartext with CMAC. The second half encrypts the cleartext in CTR
+// mode with the synthetic iv as initial counter block. Encryption is
+// deterministic, a nonce can be passed as the last component of the
+// associated data.
+
+use crate::cmac::{cmac, dbl};
+use crate::ctr::{aes_ctr_from, CounterLayout};
+use crate::{AesError, KeySize, BLOCK_SIZE};
+use cipher::constant_time_eq;
+use xor::XOR;
+
+// Returns the synthetic iv followed by the ciphertext.
+pub fn encrypt_aes_siv(input: &[u8], key: &[u8], aad: &[&[u8]]) -> Result<Vec<u8>, AesError> {
+    let (mac_key, ctr_key) = split_key(key)?;
+    let mut v = s2v(mac_key, aad, input)?;
+    let ciphertext = aes_ctr_from(
+        input,
+        ctr_key,
+        &counter_block(&v),
+        CounterLayout::BigEndian128,
+    )?;
+    v.extend_from_slice(&ciphertext);
+    Ok(v)
+}
+
+pub fn decrypt_aes_siv(input: &[u8], key: &[u8], aad: &[&[u8]]) -> Result<Vec<u8>, AesError> {
+    let (mac_key, ctr_key) = split_key(key)?;
+    if input.len() < BLOCK_SIZE {
+        return Err(AesError::InputTooShort);
+    }
+
+    let (v, ciphertext) = input.split_at(BLOCK_SIZE);
+    let cleartext = aes_ctr_from(
+        ciphertext,
+        ctr_key,
+        &counter_block(v),
+        CounterLayout::BigEndian128,
+    )?;
+    if !constant_time_eq(&s2v(mac_key, aad, &cleartext)?, v) {
+        return Err(AesError::AuthenticationFailed);
+    }
+    Ok(cleartext)
+}
+
+fn split_key(key: &[u8]) -> Result<(&[u8], &[u8]), AesError> {
+    if !key.len().is_multiple_of(2) {
+        return Err(AesError::InvalidKeyLength { len: key.len() });
+    }
+    KeySize::from_key_len(key.len() / 2)
+        .map_err(|_| AesError::InvalidKeyLength { len: key.len() })?;
+    Ok(key.split_at(key.len() / 2))
+}
+
+fn s2v(key: &[u8], aad: &[&[u8]], input: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut d = cmac(key, &[0; BLOCK_SIZE])?;
+    for component in aad {
+        d = dbl(&d).xor(&cmac(key, component)?);
+    }
+
+    let t = if input.len() >= BLOCK_SIZE {
+        // xorend: d is xor'ed onto the last BLOCK_SIZE bytes of the input
+        let mut t = input.to_vec();
+        let n = t.len() - BLOCK_SIZE;
+        t[n..].copy_from_slice(&input[n..].xor(&d));
+        t
+    } else {
+        let mut padded = input.to_vec();
+        padded.push(0x80);
+        padded.resize(BLOCK_SIZE, 0);
+        dbl(&d).xor(&padded)
+    };
+    cmac(key, &t)
+}
+
+// The 31st and 63rd bit (counted from the right) are cleared so that
+// implementations with 32-bit or 64-bit counters can be used.
+fn counter_block(v: &[u8]) -> Vec<u8> {
+    let mut q = v.to_vec();
+    q[8] &= 0x7f;
+    q[12] &= 0x7f;
+    q
+}
+
+#[test]
+fn test_siv() {
+    use serialize::from_hex;
+
+    // Test vectors from RFC 5297, Appendix A
+    let key = from_hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+    let aad = from_hex("101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+    let cleartext = from_hex("112233445566778899aabbccddee").unwrap();
+    let ciphertext =
+        from_hex("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c").unwrap();
+    assert_eq!(
+        ciphertext,
+        encrypt_aes_siv(&cleartext, &key, &[&aad]).unwrap()
+    );
+    assert_eq!(
+        cleartext,
+        decrypt_aes_siv(&ciphertext, &key, &[&aad]).unwrap()
+    );
+
+    let key = from_hex("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f").unwrap();
+    let aad1 = from_hex(
+        "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa9988\
+         7766554433221100",
+    )
+    .unwrap();
+    let aad2 = from_hex("102030405060708090a0").unwrap();
+    let nonce = from_hex("09f911029d74e35bd84156c5635688c0").unwrap();
+    let cleartext = from_hex(
+        "7468697320697320736f6d6520706c61696e7465787420746f20656e63727970\
+         74207573696e67205349562d414553",
+    )
+    .unwrap();
+    let mut ciphertext = from_hex(
+        "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+         dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d",
+    )
+    .unwrap();
+    let aad: [&[u8]; 3] = [&aad1, &aad2, &nonce];
+    assert_eq!(ciphertext, encrypt_aes_siv(&cleartext, &key, &aad).unwrap());
+    assert_eq!(cleartext, decrypt_aes_siv(&ciphertext, &key, &aad).unwrap());
+
+    ciphertext[20] ^= 1;
+    match decrypt_aes_siv(&ciphertext, &key, &aad) {
+        Err(AesError::AuthenticationFailed) => (),
+        _ => panic!("expected AuthenticationFailed"),
+    }
+}
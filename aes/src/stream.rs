@@ -0,0 +1,339 @@
+// Streaming adapters for CBC and CTR mode. They process their input block by
+// block, so only a constant amount of data is kept in memory regardless of
+// the length of the stream.
+
+use std::cmp;
+use std::io;
+use std::io::{Read, Write};
+
+use crate::ctr::{CounterLayout, Ctr};
+use crate::{pad_inplace, unpad_inplace, Aes, AesError, BLOCK_SIZE};
+use cipher::StreamCipher;
+use xor::XOR;
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+impl From<AesError> for io::Error {
+    fn from(err: AesError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+// Encrypts everything written to it in CBC mode and passes the ciphertext on
+// to `inner`. Incomplete blocks are buffered. The padding is only added by
+// `finish`, which therefore always needs to be called.
+pub struct CbcEncryptor<W: Write> {
+    inner: W,
+    aes: Aes,
+    previous: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> CbcEncryptor<W> {
+    pub fn new(inner: W, key: &[u8], iv: &[u8]) -> Result<Self, AesError> {
+        let aes = Aes::new(key)?;
+        if iv.len() != BLOCK_SIZE {
+            return Err(AesError::IvNotBlockSize);
+        }
+
+        Ok(CbcEncryptor {
+            inner,
+            aes,
+            previous: iv.to_vec(),
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+        })
+    }
+
+    // Pads and encrypts the remaining buffered data and returns the
+    // underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut last = std::mem::take(&mut self.buffer);
+        pad_inplace(&mut last, BLOCK_SIZE as u8)?;
+        let ciphertext = cbc_encrypt_blocks(&last, &self.aes, &mut self.previous)?;
+        self.inner.write_all(&ciphertext)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for CbcEncryptor<W> {
+    // The state is only updated once the ciphertext has been written, so that
+    // a failed write can be retried with the same buf.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        let full_len = self.buffer.len() - self.buffer.len() % BLOCK_SIZE;
+        let mut previous = self.previous.clone();
+        let written = cbc_encrypt_blocks(&self.buffer[..full_len], &self.aes, &mut previous)
+            .map_err(io::Error::from)
+            .and_then(|ciphertext| self.inner.write_all(&ciphertext));
+        if let Err(err) = written {
+            self.buffer.truncate(self.buffer.len() - buf.len());
+            return Err(err);
+        }
+
+        self.previous = previous;
+        self.buffer.drain(..full_len);
+        Ok(buf.len())
+    }
+
+    // Incomplete blocks cannot be flushed before the stream is finished.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn cbc_encrypt_blocks(
+    input: &[u8],
+    aes: &Aes,
+    previous: &mut Vec<u8>,
+) -> Result<Vec<u8>, AesError> {
+    let mut ciphertext = Vec::with_capacity(input.len());
+    for block in input.chunks(BLOCK_SIZE) {
+        *previous = aes.encrypt(&block.xor(previous))?;
+        ciphertext.extend_from_slice(previous);
+    }
+    Ok(ciphertext)
+}
+
+// Decrypts a CBC ciphertext read from `inner`. The last block is held back
+// until `inner` reaches EOF because only then it is known that it contains
+// the padding.
+pub struct CbcDecryptor<R: Read> {
+    inner: R,
+    aes: Aes,
+    previous: Vec<u8>,
+    ciphertext: Vec<u8>,
+    cleartext: Vec<u8>,
+    position: usize,
+    eof: bool,
+    // Set if a block could not be decrypted, so that later reads return the
+    // error again instead of EOF or cleartext following the failed block.
+    failed: Option<AesError>,
+}
+
+impl<R: Read> CbcDecryptor<R> {
+    pub fn new(inner: R, key: &[u8], iv: &[u8]) -> Result<Self, AesError> {
+        let aes = Aes::new(key)?;
+        if iv.len() != BLOCK_SIZE {
+            return Err(AesError::IvNotBlockSize);
+        }
+
+        Ok(CbcDecryptor {
+            inner,
+            aes,
+            previous: iv.to_vec(),
+            ciphertext: Vec::with_capacity(READ_CHUNK_SIZE + BLOCK_SIZE),
+            cleartext: Vec::with_capacity(READ_CHUNK_SIZE),
+            position: 0,
+            eof: false,
+            failed: None,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_cleartext(&mut self) -> io::Result<()> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let n = self.inner.read(&mut chunk)?;
+        self.cleartext.clear();
+        self.position = 0;
+
+        let decrypted = if n == 0 {
+            self.eof = true;
+            self.decrypt_last_blocks()
+        } else {
+            self.ciphertext.extend_from_slice(&chunk[..n]);
+            let held_back = match self.ciphertext.len() % BLOCK_SIZE {
+                0 => BLOCK_SIZE,
+                r => r,
+            };
+            let len = self.ciphertext.len().saturating_sub(held_back);
+            self.decrypt_blocks(len)
+        };
+
+        match decrypted {
+            Ok(cleartext) => {
+                self.cleartext = cleartext;
+                Ok(())
+            }
+            Err(err) => {
+                self.failed = Some(err.clone());
+                Err(err.into())
+            }
+        }
+    }
+
+    fn decrypt_last_blocks(&mut self) -> Result<Vec<u8>, AesError> {
+        if !self.ciphertext.len().is_multiple_of(BLOCK_SIZE) {
+            return Err(AesError::InputNotMultipleOfBlockSize);
+        }
+        let mut cleartext = self.decrypt_blocks(self.ciphertext.len())?;
+        unpad_inplace(&mut cleartext, BLOCK_SIZE as u8)?;
+        Ok(cleartext)
+    }
+
+    // Decrypts and removes the first `len` bytes of the buffered ciphertext.
+    fn decrypt_blocks(&mut self, len: usize) -> Result<Vec<u8>, AesError> {
+        let mut cleartext = Vec::with_capacity(len);
+        for block in self.ciphertext[..len].chunks(BLOCK_SIZE) {
+            cleartext.extend_from_slice(&self.aes.decrypt(block)?.xor(&self.previous));
+            self.previous = block.to_vec();
+        }
+        self.ciphertext.drain(..len);
+        Ok(cleartext)
+    }
+}
+
+impl<R: Read> Read for CbcDecryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(err) = &self.failed {
+            return Err(err.clone().into());
+        }
+        while self.position == self.cleartext.len() && !self.eof {
+            self.fill_cleartext()?;
+        }
+
+        let n = cmp::min(buf.len(), self.cleartext.len() - self.position);
+        buf[..n].copy_from_slice(&self.cleartext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+// Applies the CTR keystream to everything read from or written to `inner`.
+// As encryption and decryption coincide in CTR mode, the same type serves
+// both purposes.
+pub struct CtrStream<T> {
+    inner: T,
+    ctr: Ctr,
+}
+
+impl<T> CtrStream<T> {
+    pub fn new(
+        inner: T,
+        key: &[u8],
+        nonce: &[u8],
+        initial_counter: u128,
+        layout: CounterLayout,
+    ) -> Result<Self, AesError> {
+        Ok(CtrStream {
+            inner,
+            ctr: Ctr::new(key, nonce, initial_counter, layout)?,
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CtrStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.ctr.apply_keystream(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for CtrStream<W> {
+    // The position is only advanced once the ciphertext has been written, so
+    // that a failed write can be retried with the same buf.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = buf.to_vec();
+        let position = self.ctr.position();
+        self.ctr.apply_keystream_at(position, &mut data)?;
+        self.inner.write_all(&data)?;
+        self.ctr.seek(position + buf.len() as u64);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// A writer which fails the first write
+#[cfg(test)]
+struct FailOnce(Vec<u8>, bool);
+
+#[cfg(test)]
+impl Write for FailOnce {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.1 {
+            self.1 = true;
+            return Err(io::Error::other("first write"));
+        }
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_cbc_stream() {
+    use crate::{Aes128, MODE};
+
+    let key = b"YELLOW SUBMARINE";
+    let iv = [7; BLOCK_SIZE];
+    let cleartext: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let expected = cleartext.encrypt(key, Some(&iv), MODE::CBC).unwrap();
+
+    let mut encryptor = CbcEncryptor::new(Vec::new(), key, &iv).unwrap();
+    for chunk in cleartext.chunks(7) {
+        encryptor.write_all(chunk).unwrap();
+    }
+    let ciphertext = encryptor.finish().unwrap();
+    assert_eq!(expected, ciphertext);
+
+    let mut decryptor = CbcDecryptor::new(&ciphertext[..], key, &iv).unwrap();
+    let mut decrypted = Vec::new();
+    let mut buf = [0; 5];
+    loop {
+        let n = decryptor.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        decrypted.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(cleartext, decrypted);
+
+    let mut decryptor = CbcDecryptor::new(&ciphertext[..ciphertext.len() - 1], key, &iv).unwrap();
+    assert!(decryptor.read_to_end(&mut Vec::new()).is_err());
+    assert!(decryptor.read(&mut buf).is_err());
+
+    let mut encryptor = CbcEncryptor::new(FailOnce(Vec::new(), false), key, &iv).unwrap();
+    assert!(encryptor.write_all(&cleartext[..20]).is_err());
+    encryptor.write_all(&cleartext).unwrap();
+    assert_eq!(expected, encryptor.finish().unwrap().0);
+}
+
+#[test]
+fn test_ctr_stream() {
+    use crate::ctr::aes_ctr_with_nonce;
+
+    let key = [1; 32];
+    let nonce = [2; 12];
+    let layout = CounterLayout::BigEndian32;
+    let cleartext: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let expected = aes_ctr_with_nonce(&cleartext, &key, &nonce, 5, layout).unwrap();
+
+    let mut writer = CtrStream::new(Vec::new(), &key, &nonce, 5, layout).unwrap();
+    for chunk in cleartext.chunks(13) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(expected, writer.into_inner());
+
+    let mut reader = CtrStream::new(&expected[..], &key, &nonce, 5, layout).unwrap();
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted).unwrap();
+    assert_eq!(cleartext, decrypted);
+
+    let mut writer = CtrStream::new(FailOnce(Vec::new(), false), &key, &nonce, 5, layout).unwrap();
+    assert!(writer.write_all(&cleartext[..20]).is_err());
+    writer.write_all(&cleartext).unwrap();
+    assert_eq!(expected, writer.into_inner().0);
+}
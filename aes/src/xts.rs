@@ -0,0 +1,179 @@
+// XTS-AES as specified in IEEE 1619. Each sector (data unit) is encrypted
+// independently under a tweak derived from its index, which makes the mode
+// suitable for random access to encrypted storage. A final partial block is
+// handled with ciphertext stealing, so sectors do not need to be a multiple
+// of the block size, but they need to be at least one block long.
+
+use crate::{Aes, AesError, KeySize, BLOCK_SIZE};
+use xor::XOR;
+
+pub struct Xts {
+    data_aes: Aes,
+    tweak_aes: Aes,
+}
+
+impl Xts {
+    // The standard only defines XTS-AES-128 and XTS-AES-256, so both keys
+    // need to have the same length of either 16 or 32 bytes.
+    pub fn new(data_key: &[u8], tweak_key: &[u8]) -> Result<Self, AesError> {
+        match KeySize::from_key_len(data_key.len())? {
+            KeySize::Aes128 | KeySize::Aes256 if tweak_key.len() == data_key.len() => Ok(Xts {
+                data_aes: Aes::new(data_key)?,
+                tweak_aes: Aes::new(tweak_key)?,
+            }),
+            KeySize::Aes128 | KeySize::Aes256 => Err(AesError::InvalidKeyLength {
+                len: tweak_key.len(),
+            }),
+            KeySize::Aes192 => Err(AesError::InvalidKeyLength {
+                len: data_key.len(),
+            }),
+        }
+    }
+
+    // The sector index is encoded as a 128-bit little-endian tweak.
+    pub fn encrypt_sector(&self, sector: u128, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.encrypt_with_tweak(&sector.to_le_bytes(), data)
+    }
+
+    pub fn decrypt_sector(&self, sector: u128, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.decrypt_with_tweak(&sector.to_le_bytes(), data)
+    }
+
+    pub fn encrypt_with_tweak(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut tweaks = self.tweaks(tweak, data.len())?;
+        let (full_len, partial_len) = split_len(data.len());
+
+        let mut ciphertext = Vec::with_capacity(data.len());
+        for (block, t) in data[..full_len].chunks(BLOCK_SIZE).zip(tweaks.iter()) {
+            ciphertext.extend_from_slice(&self.encrypt_block(block, t)?);
+        }
+
+        if partial_len > 0 {
+            // The last full ciphertext block donates its tail to pad the
+            // partial block and is then truncated.
+            let last_tweak = tweaks.pop().unwrap(); // unwrap is ok
+            let stolen = ciphertext.split_off(full_len - BLOCK_SIZE);
+            let mut block = data[full_len..].to_vec();
+            block.extend_from_slice(&stolen[partial_len..]);
+            ciphertext.extend_from_slice(&self.encrypt_block(&block, &last_tweak)?);
+            ciphertext.extend_from_slice(&stolen[..partial_len]);
+        }
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_with_tweak(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AesError> {
+        let tweaks = self.tweaks(tweak, data.len())?;
+        let (full_len, partial_len) = split_len(data.len());
+        let regular_len = if partial_len > 0 {
+            full_len - BLOCK_SIZE
+        } else {
+            full_len
+        };
+
+        let mut cleartext = Vec::with_capacity(data.len());
+        for (block, t) in data[..regular_len].chunks(BLOCK_SIZE).zip(tweaks.iter()) {
+            cleartext.extend_from_slice(&self.decrypt_block(block, t)?);
+        }
+
+        if partial_len > 0 {
+            let n = tweaks.len();
+            let last = self.decrypt_block(&data[regular_len..full_len], &tweaks[n - 1])?;
+            let mut block = data[full_len..].to_vec();
+            block.extend_from_slice(&last[partial_len..]);
+            cleartext.extend_from_slice(&self.decrypt_block(&block, &tweaks[n - 2])?);
+            cleartext.extend_from_slice(&last[..partial_len]);
+        }
+        Ok(cleartext)
+    }
+
+    fn encrypt_block(&self, block: &[u8], tweak: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ok(self.data_aes.encrypt(&block.xor(tweak))?.xor(tweak))
+    }
+
+    fn decrypt_block(&self, block: &[u8], tweak: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ok(self.data_aes.decrypt(&block.xor(tweak))?.xor(tweak))
+    }
+
+    // Returns one tweak value per (full or partial) block of a sector of
+    // length len. Consecutive values differ by a factor of the primitive
+    // element alpha of GF(2^128).
+    fn tweaks(&self, tweak: &[u8], len: usize) -> Result<Vec<Vec<u8>>, AesError> {
+        if tweak.len() != BLOCK_SIZE {
+            return Err(AesError::IvNotBlockSize);
+        }
+        if len < BLOCK_SIZE {
+            return Err(AesError::InputTooShort);
+        }
+
+        let mut t = self.tweak_aes.encrypt(tweak)?;
+        let mut tweaks = Vec::with_capacity(len.div_ceil(BLOCK_SIZE));
+        for _ in 0..len.div_ceil(BLOCK_SIZE) {
+            tweaks.push(t.clone());
+            multiply_by_alpha(&mut t);
+        }
+        Ok(tweaks)
+    }
+}
+
+// Returns the length of the part consisting of full blocks and the length of
+// the remaining partial block.
+fn split_len(len: usize) -> (usize, usize) {
+    let partial_len = len % BLOCK_SIZE;
+    (len - partial_len, partial_len)
+}
+
+// The tweak is interpreted as a little-endian polynomial over GF(2) and
+// reduced modulo x^128 + x^7 + x^2 + x + 1.
+fn multiply_by_alpha(t: &mut [u8]) {
+    let mut carry = 0;
+    for b in t.iter_mut() {
+        let next_carry = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next_carry;
+    }
+    if carry != 0 {
+        t[0] ^= 0x87;
+    }
+}
+
+#[test]
+fn test_xts() {
+    use serialize::from_hex;
+
+    // Vector 1 from IEEE 1619, Annex B
+    let xts = Xts::new(&[0; 16], &[0; 16]).unwrap();
+    let ciphertext =
+        from_hex("917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e").unwrap();
+    assert_eq!(ciphertext, xts.encrypt_sector(0, &[0; 32]).unwrap());
+    assert_eq!(vec![0; 32], xts.decrypt_sector(0, &ciphertext).unwrap());
+
+    // Sectors with a partial last block, checked against OpenSSL
+    let xts = Xts::new(
+        &from_hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0").unwrap(),
+        &from_hex("bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0").unwrap(),
+    )
+    .unwrap();
+    let vectors = [
+        (17, "641610679dcbf92e505c41333fb06c2a95"),
+        (
+            50,
+            "95c871f6522469cc737109594ab0feda383a90c3320b91b5ba5bc8bcf089a09e\
+             72d976c441560744c6e97b0dd3364edcdd10",
+        ),
+    ];
+    for &(len, ciphertext) in vectors.iter() {
+        let cleartext: Vec<u8> = (0..len).collect();
+        let ciphertext = from_hex(ciphertext).unwrap();
+        assert_eq!(
+            ciphertext,
+            xts.encrypt_sector(0x9a_7856_3412, &cleartext).unwrap()
+        );
+        assert_eq!(
+            cleartext,
+            xts.decrypt_sector(0x9a_7856_3412, &ciphertext).unwrap()
+        );
+    }
+
+    assert!(xts.encrypt_sector(0, &[0; 15]).is_err());
+    assert!(Xts::new(&[0; 16], &[0; 32]).is_err());
+}
@@ -0,0 +1,296 @@
+// ChaCha20 and the ChaCha20-Poly1305 AEAD as specified in RFC 8439. Unlike
+// the aes crate this is implemented entirely in software.
+
+extern crate cipher;
+extern crate rand;
+
+pub mod poly1305;
+
+use cipher::{constant_time_eq, StreamCipher};
+use poly1305::poly1305;
+use rand::Rng;
+
+use std::error;
+use std::fmt;
+
+pub const KEY_SIZE: usize = 32;
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+pub const BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum ChaChaError {
+    InvalidKeyLength { len: usize },
+    InvalidNonceLength { len: usize },
+    CounterOverflow,
+    AuthenticationFailed,
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for ChaChaError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl fmt::Display for ChaChaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChaChaError::InvalidKeyLength { len } => {
+                write!(f, "key length {} is not equal to {}", len, KEY_SIZE)
+            }
+            ChaChaError::InvalidNonceLength { len } => {
+                write!(f, "nonce length {} is not equal to {}", len, NONCE_SIZE)
+            }
+            ChaChaError::CounterOverflow => write!(f, "block counter overflow"),
+            ChaChaError::AuthenticationFailed => write!(f, "authentication tag mismatch"),
+        }
+    }
+}
+
+// ChaCha20 as a StreamCipher, starting at the given block counter. The
+// 32-bit counter must not wrap around, so applying more than 256 GiB of
+// keystream panics.
+#[derive(Clone)]
+pub struct ChaCha20 {
+    // The constants, key and nonce
+    state: [u32; 16],
+    // The counter of the next keystream block
+    counter: u64,
+    keystream: [u8; BLOCK_SIZE],
+    // The number of bytes of the current keystream block already used
+    offset: usize,
+}
+
+impl ChaCha20 {
+    pub fn new(key: &[u8], nonce: &[u8], initial_counter: u32) -> Result<Self, ChaChaError> {
+        Ok(ChaCha20 {
+            state: initial_state(key, nonce)?,
+            counter: u64::from(initial_counter),
+            keystream: [0; BLOCK_SIZE],
+            offset: BLOCK_SIZE,
+        })
+    }
+}
+
+impl StreamCipher for ChaCha20 {
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            if self.offset == BLOCK_SIZE {
+                assert!(self.counter < 1 << 32, "ChaCha20 block counter overflow");
+                self.keystream = block(&self.state, self.counter as u32);
+                self.counter += 1;
+                self.offset = 0;
+            }
+            *b ^= self.keystream[self.offset];
+            self.offset += 1;
+        }
+    }
+}
+
+// Encrypts or decrypts input with the keystream starting at the given block
+// counter. The 32-bit counter must not wrap around.
+pub fn chacha20(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    initial_counter: u32,
+) -> Result<Vec<u8>, ChaChaError> {
+    let mut cipher = ChaCha20::new(key, nonce, initial_counter)?;
+    let blocks_count = input.len().div_ceil(BLOCK_SIZE) as u64;
+    if u64::from(initial_counter) + blocks_count > 1 << 32 {
+        return Err(ChaChaError::CounterOverflow);
+    }
+    Ok(cipher.process(input))
+}
+
+pub fn encrypt_chacha20_poly1305(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ChaChaError> {
+    let ciphertext = chacha20(input, key, nonce, 1)?;
+    let tag = compute_tag(key, nonce, aad, &ciphertext)?;
+    Ok((ciphertext, tag))
+}
+
+pub fn decrypt_chacha20_poly1305(
+    input: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>, ChaChaError> {
+    let expected_tag = compute_tag(key, nonce, aad, input)?;
+    if !constant_time_eq(&expected_tag, tag) {
+        return Err(ChaChaError::AuthenticationFailed);
+    }
+    chacha20(input, key, nonce, 1)
+}
+
+// The one-time Poly1305 key is the first half of the keystream block with
+// counter 0. The MAC covers the associated data and the ciphertext, each
+// padded to 16 bytes, followed by their lengths.
+fn compute_tag(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, ChaChaError> {
+    let otk = block(&initial_state(key, nonce)?, 0);
+
+    let mut mac_data = Vec::with_capacity(aad.len() + ciphertext.len() + 48);
+    for data in &[aad, ciphertext] {
+        mac_data.extend_from_slice(data);
+        mac_data.resize(mac_data.len().next_multiple_of(16), 0);
+    }
+    mac_data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    mac_data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly1305(&otk[..32], &mac_data)
+}
+
+// The constants, key and nonce of the state. The block counter at index 12 is
+// filled in by `block`.
+fn initial_state(key: &[u8], nonce: &[u8]) -> Result<[u32; 16], ChaChaError> {
+    if key.len() != KEY_SIZE {
+        return Err(ChaChaError::InvalidKeyLength { len: key.len() });
+    }
+    if nonce.len() != NONCE_SIZE {
+        return Err(ChaChaError::InvalidNonceLength { len: nonce.len() });
+    }
+
+    let mut state = [0; 16];
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for (word, chunk) in state[4..12].iter_mut().zip(key.chunks(4)) {
+        *word = read_u32_le(chunk);
+    }
+    for (word, chunk) in state[13..].iter_mut().zip(nonce.chunks(4)) {
+        *word = read_u32_le(chunk);
+    }
+    Ok(state)
+}
+
+fn block(state: &[u32; 16], counter: u32) -> [u8; BLOCK_SIZE] {
+    let mut input = *state;
+    input[12] = counter;
+
+    let mut x = input;
+    for _ in 0..10 {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+
+    let mut output = [0; BLOCK_SIZE];
+    for (i, chunk) in output.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&x[i].wrapping_add(input[i]).to_le_bytes());
+    }
+    output
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+fn read_u32_le(u: &[u8]) -> u32 {
+    u32::from_le_bytes([u[0], u[1], u[2], u[3]])
+}
+
+pub fn random_key() -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    rng.gen_iter().take(KEY_SIZE).collect()
+}
+
+#[test]
+fn test_chacha20() {
+    use serialize::from_hex;
+
+    let key: Vec<u8> = (0..32).collect();
+
+    // RFC 8439, section 2.3.2
+    let nonce = from_hex("000000090000004a00000000").unwrap();
+    assert_eq!(
+        from_hex(
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        )
+        .unwrap(),
+        chacha20(&[0; BLOCK_SIZE], &key, &nonce, 1).unwrap()
+    );
+
+    // RFC 8439, section 2.4.2
+    let nonce = from_hex("000000000000004a00000000").unwrap();
+    let cleartext = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+                      one tip for the future, sunscreen would be it.";
+    let ciphertext = from_hex(
+        "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+         f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+         07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+         5af90bbf74a35be6b40b8eedf2785e42874d",
+    )
+    .unwrap();
+    assert_eq!(ciphertext, chacha20(cleartext, &key, &nonce, 1).unwrap());
+    assert_eq!(
+        cleartext.as_ref(),
+        &chacha20(&ciphertext, &key, &nonce, 1).unwrap()[..]
+    );
+
+    // The stream cipher gives the same result for any split of the input.
+    let mut cipher = ChaCha20::new(&key, &nonce, 1).unwrap();
+    let mut buf = cleartext.to_vec();
+    for chunk in buf.chunks_mut(7) {
+        cipher.apply_keystream(chunk);
+    }
+    assert_eq!(ciphertext, buf);
+
+    assert!(chacha20(&[], &key, &nonce, u32::MAX).is_ok());
+    assert!(chacha20(&[0; 65], &key, &nonce, u32::MAX).is_err());
+    assert!(chacha20(&[], &random_key(), &nonce, 0).is_ok());
+}
+
+#[test]
+fn test_chacha20_poly1305() {
+    use serialize::from_hex;
+
+    // RFC 8439, section 2.8.2
+    let key: Vec<u8> = (0x80..0xa0).collect();
+    let nonce = from_hex("070000004041424344454647").unwrap();
+    let aad = from_hex("50515253c0c1c2c3c4c5c6c7").unwrap();
+    let cleartext = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+                      one tip for the future, sunscreen would be it.";
+    let (ciphertext, tag) = encrypt_chacha20_poly1305(cleartext, &key, &nonce, &aad).unwrap();
+    assert_eq!(
+        from_hex(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116"
+        )
+        .unwrap(),
+        ciphertext
+    );
+    assert_eq!(from_hex("1ae10b594f09e26a7e902ecbd0600691").unwrap(), tag);
+    assert_eq!(
+        cleartext.as_ref(),
+        &decrypt_chacha20_poly1305(&ciphertext, &key, &nonce, &aad, &tag).unwrap()[..]
+    );
+
+    let mut tampered = ciphertext.clone();
+    tampered[0] ^= 1;
+    assert!(decrypt_chacha20_poly1305(&tampered, &key, &nonce, &aad, &tag).is_err());
+    assert!(decrypt_chacha20_poly1305(&ciphertext, &key, &nonce, &[], &tag).is_err());
+}
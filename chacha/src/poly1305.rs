@@ -0,0 +1,125 @@
+// The Poly1305 one-time authenticator from RFC 8439. The accumulator is kept
+// in five 26-bit limbs so that all products fit into a u64.
+
+use crate::ChaChaError;
+
+pub const KEY_SIZE: usize = 32;
+
+const MASK_26: u64 = (1 << 26) - 1;
+
+// The key consists of r followed by s. It must never be used for more than
+// one message.
+pub fn poly1305(key: &[u8], msg: &[u8]) -> Result<Vec<u8>, ChaChaError> {
+    if key.len() != KEY_SIZE {
+        return Err(ChaChaError::InvalidKeyLength { len: key.len() });
+    }
+
+    let r = to_limbs(
+        u128::from_le_bytes(to_array(&key[..16])) & 0x0fff_fffc_0fff_fffc_0fff_fffc_0fff_ffff,
+    );
+    let s = u128::from_le_bytes(to_array(&key[16..]));
+
+    let mut h = [0u64; 5];
+    for chunk in msg.chunks(16) {
+        let mut block = [0; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1;
+        add_block(&mut h, &block);
+        h = mul_reduce(&h, &r);
+    }
+
+    let tag = (to_u128(&freeze(&h))).wrapping_add(s);
+    Ok(tag.to_le_bytes().to_vec())
+}
+
+fn to_array(u: &[u8]) -> [u8; 16] {
+    let mut array = [0; 16];
+    array.copy_from_slice(u);
+    array
+}
+
+fn to_limbs(x: u128) -> [u64; 5] {
+    let mut limbs = [0; 5];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        *limb = (x >> (26 * i)) as u64 & MASK_26;
+    }
+    limbs
+}
+
+// Adds a block of up to 130 bits, given as 17 little-endian bytes.
+fn add_block(h: &mut [u64; 5], block: &[u8; 17]) {
+    let low = u128::from_le_bytes(to_array(&block[..16]));
+    let b = to_limbs(low);
+    for (x, y) in h.iter_mut().zip(b.iter()) {
+        *x += y;
+    }
+    h[4] += u64::from(block[16]) << 24;
+}
+
+// Computes h * r modulo 2^130 - 5. Since 2^130 = 5 modulo p, the limbs
+// wrapping around are multiplied by 5.
+fn mul_reduce(h: &[u64; 5], r: &[u64; 5]) -> [u64; 5] {
+    let mut d = [0u64; 5];
+    for (i, di) in d.iter_mut().enumerate() {
+        for (j, hj) in h.iter().enumerate() {
+            let k = (i + 5 - j) % 5;
+            let factor = if j > i { 5 * r[k] } else { r[k] };
+            *di += hj * factor;
+        }
+    }
+    carry(&mut d);
+    d
+}
+
+fn carry(d: &mut [u64; 5]) {
+    let mut c = 0;
+    for x in d.iter_mut() {
+        *x += c;
+        c = *x >> 26;
+        *x &= MASK_26;
+    }
+    d[0] += c * 5;
+    c = d[0] >> 26;
+    d[0] &= MASK_26;
+    d[1] += c;
+}
+
+// Fully reduces h modulo 2^130 - 5 by conditionally subtracting p.
+fn freeze(h: &[u64; 5]) -> [u64; 5] {
+    let mut h = *h;
+    carry(&mut h);
+    carry(&mut h);
+
+    let mut g = [0u64; 5];
+    let mut c = 5;
+    for (x, y) in g.iter_mut().zip(h.iter()) {
+        *x = y + c;
+        c = *x >> 26;
+        *x &= MASK_26;
+    }
+    // g = h + 5 - 2^130 does not underflow iff h >= p.
+    let use_g = 0u64.wrapping_sub(c);
+    for (x, y) in h.iter_mut().zip(g.iter()) {
+        *x = (*x & !use_g) | (y & use_g);
+    }
+    h
+}
+
+// The lower 128 bits of h.
+fn to_u128(h: &[u64; 5]) -> u128 {
+    h.iter()
+        .enumerate()
+        .fold(0, |acc, (i, &limb)| acc | (u128::from(limb) << (26 * i)))
+}
+
+#[test]
+fn test_poly1305() {
+    use serialize::from_hex;
+
+    // RFC 8439, section 2.5.2
+    let key = from_hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b").unwrap();
+    assert_eq!(
+        from_hex("a8061dc1305136c6c22b8baf0c0127a9").unwrap(),
+        poly1305(&key, b"Cryptographic Forum Research Group").unwrap()
+    );
+}
@@ -2,6 +2,10 @@ extern crate byteorder;
 
 extern crate aes;
 extern crate bignum;
+extern crate chacha;
+extern crate cipher;
+extern crate der;
+extern crate des;
 extern crate diffie_hellman;
 extern crate dsa;
 extern crate mac;
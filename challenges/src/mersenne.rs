@@ -1,3 +1,5 @@
+use cipher::StreamCipher;
+
 pub const STATE_SIZE: usize = 624;
 
 // The following code is a direct translation of the pseudocode found on Wikipedia
@@ -99,6 +101,37 @@ impl Iterator for MersenneTwister {
     }
 }
 
+// The stream cipher from challenge 24. The keystream consists of the outputs
+// of a Mersenne Twister seeded with the key, each as four little-endian bytes.
+pub struct MersenneStream {
+    mt: MersenneTwister,
+    keystream: [u8; 4],
+    used: usize,
+}
+
+impl MersenneStream {
+    pub fn new(seed: u32) -> Self {
+        MersenneStream {
+            mt: MersenneTwister::initialize(seed),
+            keystream: [0; 4],
+            used: 4,
+        }
+    }
+}
+
+impl StreamCipher for MersenneStream {
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            if self.used == self.keystream.len() {
+                self.keystream = self.mt.next().unwrap().to_le_bytes(); // unwrap is ok
+                self.used = 0;
+            }
+            *b ^= self.keystream[self.used];
+            self.used += 1;
+        }
+    }
+}
+
 fn temper(mut y: u32) -> u32 {
     y ^= y >> 11;
     y ^= (y << 7) & 0x9d2c_5680;
@@ -6,6 +6,7 @@ use rand::Rng;
 use aes::BLOCK_SIZE;
 use aes::{Aes128, MODE};
 
+use cipher::{encrypt_ecb, BlockCipher};
 use serialize::from_base64;
 
 use crate::errors::*;
@@ -99,41 +100,45 @@ impl Oracle11 {
     }
 }
 
-pub struct Oracle12 {
-    common: Common,
+// The unknown string appended by the oracles of challenges 12 and 14
+fn secret_suffix() -> Result<Vec<u8>> {
+    from_base64(
+        "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRv\
+         d24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvb\
+         iBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW\
+         91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK",
+    )
+    .map_err(Into::into)
+}
+
+// The oracle works with any block cipher, so that the attack can be checked
+// against block sizes other than 16.
+pub struct Oracle12<C: BlockCipher> {
+    cipher: C,
+    suffix: Vec<u8>,
 }
 
-impl DeterministicOracle for Oracle12 {}
+impl<C: BlockCipher> DeterministicOracle for Oracle12<C> {}
 
-impl Oracle for Oracle12 {
+impl<C: BlockCipher> Oracle for Oracle12<C> {
     fn encrypt(&self, u: &[u8]) -> Result<Vec<u8>> {
-        self.common.encrypt(u)
+        let mut cleartext = Vec::with_capacity(u.len() + self.suffix.len());
+        cleartext.extend_from_slice(u);
+        cleartext.extend_from_slice(&self.suffix);
+        encrypt_ecb(&self.cipher, &cleartext).map_err(|err| err.into())
     }
 }
 
-impl Oracle12 {
-    pub fn new() -> Result<Self> {
-        let key = random_block();
-
-        let suffix = from_base64(
-            "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRv\
-             d24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvb\
-             iBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW\
-             91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK",
-        )?;
-
+impl<C: BlockCipher> Oracle12<C> {
+    pub fn new(cipher: C) -> Result<Self> {
         Ok(Oracle12 {
-            common: Common {
-                key,
-                prefix: Vec::new(),
-                suffix,
-                mode: MODE::ECB,
-            },
+            cipher,
+            suffix: secret_suffix()?,
         })
     }
 
     pub fn verify_suffix(&self, candidate: &[u8]) -> Result<()> {
-        compare_eq(&self.common.suffix[..], candidate)
+        compare_eq(&self.suffix[..], candidate)
     }
 }
 
@@ -210,12 +215,7 @@ impl Oracle14 {
         let prefix_len = rng.gen_range(1, 200);
         let prefix: Vec<u8> = rng.gen_iter().take(prefix_len).collect();
 
-        let suffix = from_base64(
-            "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRv\
-             d24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvb\
-             iBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW\
-             91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK",
-        )?;
+        let suffix = secret_suffix()?;
 
         Ok(Oracle14 {
             common: Common {
@@ -1,7 +1,7 @@
-use super::challenge03::compute_score;
 use crate::errors::*;
 use serialize::from_hex_lines;
 use std::path::Path;
+use xor::analysis::compute_score;
 use xor::XOR;
 
 pub fn run() -> Result<()> {
@@ -1,5 +1,7 @@
 use crate::errors::*;
-use aes::{chunks_count, BLOCK_SIZE};
+use aes::{random_block, Aes};
+use cipher::BlockCipher;
+use des::TripleDes;
 
 use crate::prefix_suffix_oracles::Oracle12;
 use crate::prefix_suffix_oracles::{DeterministicOracle, Oracle};
@@ -18,8 +20,9 @@ pub fn block_size<T: Oracle>(oracle: &T) -> Result<usize> {
     }
 }
 
+// An oracle without padding, for example in CTR mode, reports a block size of 1.
 fn uses_padding<T: Oracle>(oracle: &T) -> Result<bool> {
-    Ok((oracle.encrypt(&[0])?.len() - oracle.encrypt(&[])?.len()) % BLOCK_SIZE == 0)
+    Ok(block_size(oracle)? > 1)
 }
 
 pub fn prefix_plus_suffix_length<T: Oracle>(oracle: &T) -> Result<usize> {
@@ -28,8 +31,9 @@ pub fn prefix_plus_suffix_length<T: Oracle>(oracle: &T) -> Result<usize> {
         return Ok(initial);
     }
 
-    let input = [0; BLOCK_SIZE];
-    if let Some(index) = (1..=BLOCK_SIZE).find(|&i| {
+    let block_size = block_size(oracle)?;
+    let input = vec![0; block_size];
+    if let Some(index) = (1..=block_size).find(|&i| {
         if let Ok(ciphertext) = oracle.encrypt(&input[..i]) {
             initial != ciphertext.len()
         } else {
@@ -47,16 +51,19 @@ pub fn prefix_plus_suffix_length<T: Oracle>(oracle: &T) -> Result<usize> {
 }
 
 // For an oracle prepending prefix and appending suffix to its input, this function returns
-// prefix.len()/BLOCK_SIZE, that is the number of blocks fully occupied by the prefix.
+// prefix.len()/block_size, that is the number of blocks fully occupied by the prefix.
 //
 // To determine this number, we pass two different cleartexts to the oracle and count the number
 // of identical blocks at the start of the corresponding ciphertexts.
 
-fn full_prefix_blocks_count<T: DeterministicOracle>(oracle: &T) -> Result<usize> {
+fn full_prefix_blocks_count<T: DeterministicOracle>(
+    oracle: &T,
+    block_size: usize,
+) -> Result<usize> {
     if let Some(result) = oracle
         .encrypt(&[0])?
-        .chunks(BLOCK_SIZE)
-        .zip(oracle.encrypt(&[1])?.chunks(BLOCK_SIZE))
+        .chunks(block_size)
+        .zip(oracle.encrypt(&[1])?.chunks(block_size))
         .position(|(x, y)| x != y)
     {
         Ok(result)
@@ -86,17 +93,18 @@ fn full_prefix_blocks_count<T: DeterministicOracle>(oracle: &T) -> Result<usize>
 // coincide with the constant we have chosen.
 
 pub fn prefix_length<T: DeterministicOracle>(oracle: &T) -> Result<usize> {
-    let offset = full_prefix_blocks_count(oracle)? * BLOCK_SIZE;
+    let block_size = block_size(oracle)?;
+    let offset = full_prefix_blocks_count(oracle, block_size)? * block_size;
     let helper = |k: u8| -> Result<usize> {
-        let constant_block = vec![k; BLOCK_SIZE];
-        let initial = &oracle.encrypt(&constant_block)?[offset..(offset + BLOCK_SIZE)];
-        for i in 0..BLOCK_SIZE {
+        let constant_block = vec![k; block_size];
+        let initial = &oracle.encrypt(&constant_block)?[offset..(offset + block_size)];
+        for i in 0..block_size {
             let cur = oracle.encrypt(&constant_block[i + 1..])?;
-            if cur.len() < offset + BLOCK_SIZE || initial != &cur[offset..(offset + BLOCK_SIZE)] {
+            if cur.len() < offset + block_size || initial != &cur[offset..(offset + block_size)] {
                 return Ok(i);
             }
         }
-        Ok(BLOCK_SIZE)
+        Ok(block_size)
     };
 
     Ok(offset + std::cmp::min(helper(0)?, helper(1)?))
@@ -115,30 +123,32 @@ pub fn decrypt_suffix<T: DeterministicOracle>(oracle: &T) -> Result<Vec<u8>> {
     //                ↓                ↓
     // <-- prefix --> 0 ... 0 || 0 ... 0 suffix[0] || suffix[1] ...
     //                ↑          ↑
-    //            prefix_len  prefix_chunks_count*BLOCK_SIZE
+    //            prefix_len  prefix_chunks_count*block_size
     //
     // The resulting ciphertext is compared to oracle([input, u]). The u yielding a match is
     // equal to suffix[0].
 
+    let block_size = block_size(oracle)?;
     let (prefix_len, suffix_len) = prefix_and_suffix_length(oracle)?;
-    let (prefix_chunks_count, prefix_fill_len) = chunks_count(prefix_len);
+    let prefix_chunks_count = prefix_len.div_ceil(block_size);
+    let prefix_fill_len = prefix_chunks_count * block_size - prefix_len;
 
     let mut suffix = Vec::with_capacity(suffix_len);
 
-    let mut input = vec![0; prefix_fill_len + BLOCK_SIZE - 1];
-    let reference_ciphertexts = (0..BLOCK_SIZE)
+    let mut input = vec![0; prefix_fill_len + block_size - 1];
+    let reference_ciphertexts = (0..block_size)
         .map(|left_shift| oracle.encrypt(&input[left_shift..]))
         .collect::<Result<Vec<Vec<u8>>>>()?;
 
     for i in 0..suffix_len {
-        let block_index = prefix_chunks_count + i / BLOCK_SIZE;
-        let left_shift = i % BLOCK_SIZE;
+        let block_index = prefix_chunks_count + i / block_size;
+        let left_shift = i % block_size;
         for u in 0u8..=255 {
             input.push(u);
             if reference_ciphertexts[left_shift]
-                [block_index * BLOCK_SIZE..(block_index + 1) * BLOCK_SIZE]
+                [block_index * block_size..(block_index + 1) * block_size]
                 == oracle.encrypt(&input[left_shift..])?
-                    [block_index * BLOCK_SIZE..(block_index + 1) * BLOCK_SIZE]
+                    [block_index * block_size..(block_index + 1) * block_size]
             {
                 suffix.push(u);
                 break;
@@ -150,12 +160,20 @@ pub fn decrypt_suffix<T: DeterministicOracle>(oracle: &T) -> Result<Vec<u8>> {
 }
 
 pub fn run() -> Result<()> {
-    let oracle = Oracle12::new()?;
-    if block_size(&oracle)? != BLOCK_SIZE {
+    run_with_cipher(Aes::new(&random_block())?)?;
+
+    // The same attack against a cipher with 8 byte blocks
+    run_with_cipher(TripleDes::new(&des::random_key(24))?)
+}
+
+fn run_with_cipher<C: BlockCipher>(cipher: C) -> Result<()> {
+    let expected_block_size = cipher.block_size();
+    let oracle = Oracle12::new(cipher)?;
+    if block_size(&oracle)? != expected_block_size {
         return Err("oracle does not use expected block size".into());
     }
 
-    if !(uses_ecb(&oracle, 0)?) {
+    if !(uses_ecb(&oracle, expected_block_size, 0)?) {
         return Err("oracle does not use ECB".into());
     }
 
@@ -13,7 +13,7 @@ pub fn run() -> Result<()> {
         return Err("oracle does not use expected block size".into());
     }
 
-    if !(uses_ecb(&oracle, 200)?) {
+    if !(uses_ecb(&oracle, BLOCK_SIZE, 200)?) {
         return Err("oracle does not use ECB".into());
     }
     oracle.verify_suffix(&decrypt_suffix(&oracle)?)
@@ -1,20 +1,29 @@
 use crate::errors::*;
-use aes::{unpad_inplace, Aes128, AesError, BLOCK_SIZE, MODE};
+use aes::{random_block, Aes};
+use cipher::{decrypt_cbc, encrypt_cbc, unpad_pkcs7, BlockCipher, ModeError};
+use des::TripleDes;
 use rand::Rng;
 use serialize::from_base64;
 use xor::XOR;
 
-use aes::random_block;
-
-struct Server {
-    key: Vec<u8>,
+// The attack works for any block cipher in CBC mode. Triple DES is included
+// to make sure that it does not depend on a block size of 16.
+struct Server<C: BlockCipher> {
+    cipher: C,
 }
 
-impl Server {
-    fn new() -> Self {
-        Self {
-            key: random_block(),
-        }
+impl<C: BlockCipher> Server<C> {
+    fn new(cipher: C) -> Self {
+        Self { cipher }
+    }
+
+    fn random_iv(&self) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        rng.gen_iter().take(self.cipher.block_size()).collect()
+    }
+
+    fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        Ok(decrypt_cbc(&self.cipher, ciphertext, iv)?)
     }
 
     fn get_session_token(&self) -> Result<(Vec<u8>, Vec<u8>)> {
@@ -33,44 +42,43 @@ impl Server {
         let mut rng = rand::thread_rng();
         let input_index = rng.gen_range(0, inputs.len());
         let input = from_base64(inputs[input_index]).unwrap();
-        let iv = random_block();
-        let ciphertext = input.encrypt(&self.key, Some(&iv), MODE::CBC)?;
+        let iv = self.random_iv();
+        let ciphertext = encrypt_cbc(&self.cipher, &input, &iv)?;
         Ok((iv, ciphertext))
     }
 
     fn is_padding_valid(&self, iv: &[u8], ciphertext: &[u8]) -> Result<bool> {
-        if let Err(error) = ciphertext.decrypt(&self.key, Some(iv), MODE::CBC) {
-            match error {
-                AesError::InvalidPadding => Ok(false),
-                _ => Err(error.into()),
-            }
-        } else {
-            Ok(true)
+        match decrypt_cbc(&self.cipher, ciphertext, iv) {
+            Err(ModeError::InvalidPadding) => Ok(false),
+            result => result.map(|_| true).map_err(|err| err.into()),
         }
     }
 
     fn verify_solution(&self, cleartext: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<()> {
-        compare_eq(
-            &ciphertext.decrypt(&self.key, Some(iv), MODE::CBC)?[..],
-            cleartext,
-        )
+        compare_eq(&self.decrypt(iv, ciphertext)?[..], cleartext)
     }
 }
 
 pub fn run() -> Result<()> {
-    let server = Server::new();
+    run_with_cipher(Aes::new(&random_block())?)?;
+    run_with_cipher(TripleDes::new(&des::random_key(24))?)
+}
+
+fn run_with_cipher<C: BlockCipher>(cipher: C) -> Result<()> {
+    let server = Server::new(cipher);
     let (iv, ciphertext) = server.get_session_token()?;
+    let block_size = iv.len();
     let mut cleartext = vec![0; ciphertext.len()];
     let mut prev = iv.clone();
-    for (block_index, block) in ciphertext.chunks(BLOCK_SIZE).enumerate() {
-        let block_offset = block_index * BLOCK_SIZE;
-        for i in (0..BLOCK_SIZE).rev() {
-            let padding = (BLOCK_SIZE - i) as u8;
+    for (block_index, block) in ciphertext.chunks(block_size).enumerate() {
+        let block_offset = block_index * block_size;
+        for i in (0..block_size).rev() {
+            let padding = (block_size - i) as u8;
             prev[i + 1..].xor_inplace(&[(padding - 1) ^ padding]);
             for u in 0u8..=255 {
                 prev[i] ^= u;
                 if server.is_padding_valid(&prev, block)?
-                    && (i < BLOCK_SIZE - 1 || {
+                    && (i < block_size - 1 || {
                         // The last byte of the block requires a special treatment because the padding could
                         // accidentally be valid if we have for example flipped the last byte to the value 2
                         // and the second to last byte of the cleartext also happens to be a 2. We therefore
@@ -90,6 +98,6 @@ pub fn run() -> Result<()> {
         }
         prev = block.to_vec();
     }
-    unpad_inplace(&mut cleartext, BLOCK_SIZE as u8)?;
+    unpad_pkcs7(&mut cleartext, block_size)?;
     server.verify_solution(&cleartext, &iv, &ciphertext)
 }
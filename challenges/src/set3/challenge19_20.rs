@@ -1,27 +1,34 @@
 use crate::errors::*;
-use crate::set1::break_multibyte_xor_for_keysize;
+use aes::ctr::{CounterLayout, Ctr};
 use aes::random_block;
 use std::path::PathBuf;
 
-use aes::{Aes128, MODE};
+use chacha::{ChaCha20, NONCE_SIZE};
+use cipher::StreamCipher;
 use serialize::from_base64_lines;
+use xor::analysis::break_multibyte_xor_for_keysize;
 
+#[derive(Copy, Clone)]
 pub enum Exercise {
     _19,
     _20,
 }
 
-struct Encrypter {
-    key: Vec<u8>,
+// The attack only relies on the keystream being reused, so it works against
+// any stream cipher with a fixed nonce. Every cleartext is encrypted with a
+// copy of `cipher` in its initial state.
+struct Encrypter<S: StreamCipher + Clone> {
+    cipher: S,
     exercise: Exercise,
 }
 
-impl Encrypter {
-    pub fn new(exercise: Exercise) -> Self {
-        Encrypter {
-            key: random_block(),
-            exercise,
-        }
+impl<S: StreamCipher + Clone> Encrypter<S> {
+    pub fn new(exercise: Exercise, cipher: S) -> Self {
+        Encrypter { cipher, exercise }
+    }
+
+    fn encrypt(&self, cleartext: &[u8]) -> Vec<u8> {
+        self.cipher.clone().process(cleartext)
     }
 
     pub fn get_ciphertexts(&self) -> Result<Vec<Vec<u8>>> {
@@ -32,27 +39,25 @@ impl Encrypter {
         };
         input_file_path.push(input_file_name);
         let cleartexts = from_base64_lines(input_file_path.as_path())?;
-        cleartexts
-            .iter()
-            .map(|c| {
-                c.encrypt(&self.key, None, MODE::CTR)
-                    .map_err(|err| err.into())
-            })
-            .collect::<Result<Vec<Vec<u8>>>>()
+        Ok(cleartexts.iter().map(|c| self.encrypt(c)).collect())
     }
 
     pub fn verify_solution(&self, candidate_key: &[u8], size: usize) -> Result<()> {
         // TODO: The first entry of the recovered key is wrong because the distribution of first letters
         // of sentences is very different from the overall distribution of letters in a text.
-        compare_eq(
-            &vec![0; size].encrypt(&self.key, None, MODE::CTR)?[1..],
-            &candidate_key[1..],
-        )
+        compare_eq(&self.encrypt(&vec![0; size])[1..], &candidate_key[1..])
     }
 }
 
 pub fn run(exercise: Exercise) -> Result<()> {
-    let encrypter = Encrypter::new(exercise);
+    let aes_ctr = Ctr::new(&random_block(), &[0; 8], 0, CounterLayout::LittleEndian64)?;
+    run_with_cipher(exercise, aes_ctr)?;
+    let chacha20 = ChaCha20::new(&chacha::random_key(), &[0; NONCE_SIZE], 0)?;
+    run_with_cipher(exercise, chacha20)
+}
+
+fn run_with_cipher<S: StreamCipher + Clone>(exercise: Exercise, cipher: S) -> Result<()> {
+    let encrypter = Encrypter::new(exercise, cipher);
     let ciphertexts = encrypter.get_ciphertexts()?;
     let size = ciphertexts.iter().map(|c| c.len()).min().unwrap(); // unwrap is ok
     let ciphertext: Vec<u8> = ciphertexts
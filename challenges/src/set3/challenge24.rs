@@ -3,7 +3,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use rand::Rng;
 
-use crate::mersenne::MersenneTwister;
+use crate::mersenne::MersenneStream;
+use cipher::StreamCipher;
 
 use crate::errors::*;
 use xor::XOR;
@@ -18,17 +19,7 @@ fn get_ciphertext(seed: u32) -> Vec<u8> {
         v.extend_from_slice(&[b'A'; 14]);
         v
     };
-    let mt = MersenneTwister::initialize(seed);
-
-    let mut ciphertext = Vec::new();
-    let buffer = &mut [0u8; 4];
-
-    for (c, m) in cleartext.chunks(4).zip(mt) {
-        //Get underlying bytes from u32 value
-        <LittleEndian as ByteOrder>::write_u32(buffer, m);
-        ciphertext.append(&mut c.xor(buffer));
-    }
-    ciphertext
+    MersenneStream::new(seed).process(&cleartext)
 }
 
 fn seed_from_ciphertext() -> Result<()> {
@@ -55,23 +46,13 @@ fn seed_from_ciphertext() -> Result<()> {
     )
 }
 
-// Note: The following size needs to be divisible by 4 for the following code
-// to work correctly.
-
 // We work with a 128 bit token
 const TOKEN_SIZE: usize = 16;
 
 fn get_token_from_seed(seed: u32) -> [u8; TOKEN_SIZE] {
-    assert!(TOKEN_SIZE % 4 == 0);
-
+    // The token is the keystream itself
     let mut token = [0u8; TOKEN_SIZE];
-
-    let mt = MersenneTwister::initialize(seed);
-    for (c, m) in token.chunks_mut(4).zip(mt) {
-        //Get underlying bytes from u32 value
-        <LittleEndian as ByteOrder>::write_u32(c, m);
-    }
-
+    MersenneStream::new(seed).apply_keystream(&mut token);
     token
 }
 
@@ -1,7 +1,6 @@
 use std::path::Path;
 
-use aes::Aes128;
-use aes::MODE;
+use aes::ctr::{CounterLayout, Ctr};
 use xor::XOR;
 
 use serialize::from_base64_file;
@@ -12,18 +11,19 @@ use crate::errors::*;
 
 struct Encrypter {
     cleartext: Vec<u8>,
-    key: Vec<u8>,
+    ctr: Ctr,
     ciphertext: Vec<u8>,
 }
 
 impl Encrypter {
     pub fn new() -> Result<Self> {
         let cleartext = from_base64_file(Path::new("data/25.txt"))?;
-        let key = random_block();
-        let ciphertext = cleartext.encrypt(&key, None, MODE::CTR)?;
+        let ctr = Ctr::new(&random_block(), &[0; 8], 0, CounterLayout::LittleEndian64)?;
+        let mut ciphertext = cleartext.clone();
+        ctr.apply_keystream_at(0, &mut ciphertext)?;
         Ok(Encrypter {
             cleartext,
-            key,
+            ctr,
             ciphertext,
         })
     }
@@ -32,14 +32,17 @@ impl Encrypter {
         &self.ciphertext
     }
 
+    // Only the keystream for the edited range is computed.
     pub fn edit(&self, offset: usize, newtext: &[u8]) -> Result<Vec<u8>> {
-        let mut cleartext = self.cleartext.clone();
+        let mut ciphertext = self.ciphertext.clone();
         let end = offset + newtext.len();
-        if end > cleartext.len() {
+        if end > ciphertext.len() {
             return Err("input out of bounds".into());
         }
-        cleartext[offset..end].copy_from_slice(newtext);
-        Ok(cleartext.encrypt(&self.key, None, MODE::CTR)?)
+        ciphertext[offset..end].copy_from_slice(newtext);
+        self.ctr
+            .apply_keystream_at(offset as u64, &mut ciphertext[offset..end])?;
+        Ok(ciphertext)
     }
 
     pub fn verify_solution(&self, candidate_cleartext: &[u8]) -> Result<()> {
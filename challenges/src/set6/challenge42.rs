@@ -5,6 +5,8 @@ use rsa::Rsa;
 use bignum::OpensslBigNum as BigNum;
 use bignum::{BigNumExt, BigNumTrait};
 
+use der::{DigestInfo, SHA1};
+
 use sha1::{Digest, Sha1};
 
 const BITS: usize = 1024;
@@ -19,6 +21,12 @@ fn compute_hash(message: &[u8]) -> Vec<u8> {
     sha1.result().to_vec()
 }
 
+fn compute_digest_info(message: &[u8]) -> Vec<u8> {
+    DigestInfo::new(SHA1, &compute_hash(message))
+        .encode()
+        .unwrap() // unwrap is ok, SHA1 is a valid object identifier
+}
+
 impl Server {
     fn new() -> Self {
         Server {
@@ -47,7 +55,7 @@ impl Server {
         }
 
         // Verify that plaintext *starts with* (this is the flaw)
-        // 01 || ff || ff || ... || ff || 00 || hash
+        // 01 || ff || ff || ... || ff || 00 || DigestInfo
 
         if plaintext[0] != 1 {
             return false;
@@ -63,25 +71,26 @@ impl Server {
         }
         i += 1;
 
-        let hash = compute_hash(message);
-        if i + hash.len() > plaintext.len() {
-            return false;
+        // Whatever follows the DigestInfo is ignored.
+        match DigestInfo::decode_prefix(&plaintext[i..]) {
+            Ok((digest_info, _)) => {
+                digest_info.algorithm == SHA1 && digest_info.digest == compute_hash(message)
+            }
+            Err(_) => false,
         }
-
-        plaintext[i..i + hash.len()] == hash[..]
     }
 
     // Not really required for the exercise
     fn sign_message(&self, message: &[u8]) -> Vec<u8> {
-        let hash = compute_hash(message);
+        let digest_info = compute_digest_info(message);
         let len = self.rsa.n().bytes() - 1;
         let mut plaintext = Vec::with_capacity(len);
         plaintext.push(1);
-        for _ in 1..len - 1 - hash.len() {
+        for _ in 1..len - 1 - digest_info.len() {
             plaintext.push(255);
         }
         plaintext.push(0);
-        plaintext.extend_from_slice(&hash);
+        plaintext.extend_from_slice(&digest_info);
         self.rsa
             .decrypt(&BigNum::from_bytes_be(&plaintext))
             .to_bytes_be()
@@ -123,34 +132,34 @@ impl Server {
 
 #[allow(clippy::just_underscores_and_digits)]
 fn forge_signature(len: usize, message: &[u8]) -> Vec<u8> {
-    let hash = &compute_hash(message);
+    let digest_info = &compute_digest_info(message);
 
     // Search for a plaintext of length `len` of the form
-    // (F) 01 || ff || ff || ... || ff || 00 || hash || *
+    // (F) 01 || ff || ff || ... || ff || 00 || digest_info || *
     // which has a third root in the natural numbers.
     //
     // In fact we can always achieve this without any ff bytes in the prefix.
     // Interpret
-    // 01 || 00 || hash
-    // as a big-endian number x. The length of x in bytes is 2 + hash.len() and we have
-    // x <= 2^(8*(2 + hash.len())). Let l = 8(len - 2 - hash.len()) be the number
+    // 01 || 00 || digest_info
+    // as a big-endian number x. The length of x in bytes is 2 + digest_info.len() and we have
+    // x <= 2^(8*(2 + digest_info.len())). Let l = 8(len - 2 - digest_info.len()) be the number
     // of bits we can fill arbitrarily. Then the numbers of the form (F) above
     // (in big-endian) are precisely the natural numbers between
-    // x2^l and (x + 1)2^l - 1. As 8*(2 + hash.len()) is small compared to l, we
+    // x2^l and (x + 1)2^l - 1. As 8*(2 + digest_info.len()) is small compared to l, we
     // know by the general observations above that floor( crt((x + 1)k - 1) )
     // is a signature which will fool the server.
     //
     // Note that we can just as easily forge a signature with several ff bytes in the prefix
-    // as long as 8*(2 + hash.len() + number of ff bytes) < (l - 9)/2.
+    // as long as 8*(2 + digest_info.len() + number of ff bytes) < (l - 9)/2.
 
     let x = BigNum::from_bytes_be(&{
-        let mut v = Vec::with_capacity(2 + hash.len());
+        let mut v = Vec::with_capacity(2 + digest_info.len());
         v.push(1);
         v.push(0);
-        v.extend_from_slice(hash);
+        v.extend_from_slice(digest_info);
         v
     });
-    let l = 8 * (len - 2 - hash.len()) as usize;
+    let l = 8 * (len - 2 - digest_info.len()) as usize;
     let _1 = BigNum::one();
     let r = (&(&x + &_1).lsh(l) - &_1).root(3).0;
     r.to_bytes_be()
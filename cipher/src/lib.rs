@@ -0,0 +1,93 @@
+// Common interfaces for block and stream ciphers. The modes of operation in
+// `modes` work with any BlockCipher, independently of its block size, so that
+// attacks written against them are not tied to AES.
+
+extern crate xor;
+
+pub mod modes;
+
+pub use modes::{
+    add_to_counter_be, add_to_counter_le, decrypt_cbc, decrypt_ecb, encrypt_cbc, encrypt_ecb,
+    pad_pkcs7, pkcs7_padding_len, unpad_pkcs7, Ctr,
+};
+
+use std::error;
+use std::fmt;
+
+pub trait BlockCipher {
+    // The block size in bytes
+    fn block_size(&self) -> usize;
+
+    // Both functions operate on a single block in place and panic if its
+    // length is not equal to block_size().
+    fn encrypt_block(&self, block: &mut [u8]);
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+impl<C: BlockCipher + ?Sized> BlockCipher for &C {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        (**self).encrypt_block(block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        (**self).decrypt_block(block)
+    }
+}
+
+pub trait StreamCipher {
+    // XORs the next buf.len() bytes of the keystream into buf. Encryption and
+    // decryption are the same operation.
+    fn apply_keystream(&mut self, buf: &mut [u8]);
+
+    fn process(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut output = input.to_vec();
+        self.apply_keystream(&mut output);
+        output
+    }
+}
+
+// Compares all bytes regardless of where the first difference occurs, so that
+// checking an authentication tag does not leak how much of it is correct.
+pub fn constant_time_eq(expected: &[u8], actual: &[u8]) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual.iter())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[derive(Debug, Clone)]
+pub enum ModeError {
+    InvalidPadding,
+    InvalidBlockSize { len: usize },
+    InputNotMultipleOfBlockSize,
+    IvNotBlockSize,
+    InvalidNonceLength { len: usize },
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for ModeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModeError::InvalidPadding => write!(f, "invalid padding"),
+            ModeError::InvalidBlockSize { len } => write!(f, "unsupported block size {}", len),
+            ModeError::InputNotMultipleOfBlockSize => {
+                write!(f, "input length not a multiple of the block size")
+            }
+            ModeError::IvNotBlockSize => write!(f, "iv length not equal to the block size"),
+            ModeError::InvalidNonceLength { len } => write!(f, "invalid nonce length {}", len),
+        }
+    }
+}
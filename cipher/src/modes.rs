@@ -0,0 +1,257 @@
+// ECB, CBC and CTR mode for an arbitrary BlockCipher. ECB and CBC always use
+// PKCS#7 padding, so the block size must not exceed 255 bytes.
+//
+// The PKCS#7 and counter helpers are public so that the AES crate, which has
+// its own modes and padding schemes, shares the implementation.
+
+use crate::{BlockCipher, ModeError, StreamCipher};
+use xor::XOR;
+
+pub fn encrypt_ecb<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    input: &[u8],
+) -> Result<Vec<u8>, ModeError> {
+    let mut output = input.to_vec();
+    pad_pkcs7(&mut output, cipher.block_size())?;
+    for block in output.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(block);
+    }
+    Ok(output)
+}
+
+pub fn decrypt_ecb<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    input: &[u8],
+) -> Result<Vec<u8>, ModeError> {
+    let block_size = cipher.block_size();
+    if !input.len().is_multiple_of(block_size) {
+        return Err(ModeError::InputNotMultipleOfBlockSize);
+    }
+
+    let mut output = input.to_vec();
+    for block in output.chunks_mut(block_size) {
+        cipher.decrypt_block(block);
+    }
+    unpad_pkcs7(&mut output, block_size)?;
+    Ok(output)
+}
+
+pub fn encrypt_cbc<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    input: &[u8],
+    iv: &[u8],
+) -> Result<Vec<u8>, ModeError> {
+    let block_size = cipher.block_size();
+    if iv.len() != block_size {
+        return Err(ModeError::IvNotBlockSize);
+    }
+
+    let mut output = input.to_vec();
+    pad_pkcs7(&mut output, block_size)?;
+    let mut previous = iv.to_vec();
+    for block in output.chunks_mut(block_size) {
+        block.xor_inplace(&previous);
+        cipher.encrypt_block(block);
+        previous.copy_from_slice(block);
+    }
+    Ok(output)
+}
+
+pub fn decrypt_cbc<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    input: &[u8],
+    iv: &[u8],
+) -> Result<Vec<u8>, ModeError> {
+    let block_size = cipher.block_size();
+    if !input.len().is_multiple_of(block_size) {
+        return Err(ModeError::InputNotMultipleOfBlockSize);
+    }
+    if iv.len() != block_size {
+        return Err(ModeError::IvNotBlockSize);
+    }
+
+    let mut output = input.to_vec();
+    for (i, block) in output.chunks_mut(block_size).enumerate() {
+        cipher.decrypt_block(block);
+        let previous = if i == 0 {
+            iv
+        } else {
+            &input[(i - 1) * block_size..i * block_size]
+        };
+        block.xor_inplace(previous);
+    }
+    unpad_pkcs7(&mut output, block_size)?;
+    Ok(output)
+}
+
+// CTR mode as a stream cipher. The counter block consists of the nonce, which
+// fills the first half of the block, followed by a little-endian block counter
+// starting at 0. For a 16 byte block cipher this is the layout of the
+// cryptopals challenges.
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    counter: Vec<u8>,
+    keystream: Vec<u8>,
+    used: usize,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    pub fn new(cipher: C, nonce: &[u8]) -> Result<Self, ModeError> {
+        let block_size = cipher.block_size();
+        if nonce.len() != block_size / 2 {
+            return Err(ModeError::InvalidNonceLength { len: nonce.len() });
+        }
+
+        let mut counter = nonce.to_vec();
+        counter.resize(block_size, 0);
+        Ok(Ctr {
+            cipher,
+            counter,
+            keystream: vec![0; block_size],
+            used: block_size,
+        })
+    }
+
+    fn next_keystream_block(&mut self) {
+        self.keystream.copy_from_slice(&self.counter);
+        self.cipher.encrypt_block(&mut self.keystream);
+        self.used = 0;
+
+        let nonce_len = self.counter.len() / 2;
+        add_to_counter_le(&mut self.counter[nonce_len..], 1);
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for Ctr<C> {
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            if self.used == self.keystream.len() {
+                self.next_keystream_block();
+            }
+            *b ^= self.keystream[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+pub fn pad_pkcs7(u: &mut Vec<u8>, block_size: usize) -> Result<(), ModeError> {
+    if !(2..=255).contains(&block_size) {
+        return Err(ModeError::InvalidBlockSize { len: block_size });
+    }
+
+    let p = block_size - u.len() % block_size;
+    u.resize(u.len() + p, p as u8);
+    Ok(())
+}
+
+pub fn unpad_pkcs7(u: &mut Vec<u8>, block_size: usize) -> Result<(), ModeError> {
+    let p = pkcs7_padding_len(u, block_size).ok_or(ModeError::InvalidPadding)?;
+    u.truncate(u.len() - p);
+    Ok(())
+}
+
+// Returns the length of the PKCS#7 padding at the end of u, or None if u is
+// not a non-empty multiple of block_size or the padding is invalid.
+pub fn pkcs7_padding_len(u: &[u8], block_size: usize) -> Option<usize> {
+    if u.is_empty() || !u.len().is_multiple_of(block_size) {
+        return None;
+    }
+
+    let p = *u.last()? as usize;
+    if !(1..=block_size).contains(&p) || u[u.len() - p..].iter().any(|&b| b as usize != p) {
+        return None;
+    }
+    Some(p)
+}
+
+// Add n to a counter of any width, wrapping around on overflow.
+pub fn add_to_counter_le(counter: &mut [u8], n: u128) {
+    add_to_counter(counter.iter_mut(), n);
+}
+
+pub fn add_to_counter_be(counter: &mut [u8], n: u128) {
+    add_to_counter(counter.iter_mut().rev(), n);
+}
+
+// The bytes are visited from least to most significant.
+fn add_to_counter<'a>(bytes: impl Iterator<Item = &'a mut u8>, n: u128) {
+    let mut carry = n;
+    for b in bytes {
+        if carry == 0 {
+            break;
+        }
+        let sum = u128::from(*b) + (carry & 0xff);
+        *b = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+}
+
+// A toy cipher with 8 byte blocks for testing the modes. It is a bijection,
+// but of course not secure in any way.
+#[cfg(test)]
+struct RotateXor {
+    key: [u8; 8],
+}
+
+#[cfg(test)]
+impl BlockCipher for RotateXor {
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        block.xor_inplace(&self.key);
+        block.rotate_left(3);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        block.rotate_right(3);
+        block.xor_inplace(&self.key);
+    }
+}
+
+#[test]
+fn test_modes() {
+    let cipher = RotateXor { key: *b"DES KEY!" };
+    let iv = [7; 8];
+    for len in 0..=24 {
+        let input: Vec<u8> = (0..len).collect();
+
+        let ciphertext = encrypt_ecb(&cipher, &input).unwrap();
+        assert_eq!(len as usize / 8 * 8 + 8, ciphertext.len());
+        assert_eq!(input, decrypt_ecb(&cipher, &ciphertext).unwrap());
+
+        let ciphertext = encrypt_cbc(&cipher, &input, &iv).unwrap();
+        assert_eq!(input, decrypt_cbc(&cipher, &ciphertext, &iv).unwrap());
+
+        let ciphertext = Ctr::new(&cipher, &[1; 4]).unwrap().process(&input);
+        assert_eq!(input.len(), ciphertext.len());
+        assert_eq!(
+            input,
+            Ctr::new(&cipher, &[1; 4]).unwrap().process(&ciphertext)
+        );
+    }
+
+    assert!(decrypt_ecb(&cipher, &[0; 7]).is_err());
+    assert!(encrypt_cbc(&cipher, b"", &[0; 16]).is_err());
+    assert!(Ctr::new(&cipher, &[0; 8]).is_err());
+}
+
+#[test]
+fn test_helpers() {
+    let mut u = b"ICE ICE BABY".to_vec();
+    pad_pkcs7(&mut u, 16).unwrap();
+    assert_eq!(b"ICE ICE BABY\x04\x04\x04\x04", &u[..]);
+    assert_eq!(None, pkcs7_padding_len(b"ICE ICE BABY\x01\x02\x03\x04", 16));
+    assert_eq!(None, pkcs7_padding_len(&u[..15], 15));
+    unpad_pkcs7(&mut u, 16).unwrap();
+    assert_eq!(b"ICE ICE BABY", &u[..]);
+
+    let mut counter = [0xff, 0xff, 0xfe, 0xff];
+    add_to_counter_le(&mut counter, 0x0102);
+    assert_eq!([0x01, 0x01, 0xff, 0xff], counter);
+    add_to_counter_be(&mut counter, 0x0101);
+    assert_eq!([0x01, 0x02, 0x01, 0x00], counter);
+    add_to_counter_be(&mut counter, u128::MAX);
+    assert_eq!([0x01, 0x02, 0x00, 0xff], counter);
+}
@@ -0,0 +1,110 @@
+// The DigestInfo structure which is signed in PKCS #1 v1.5 signatures
+// (RFC 8017, section 9.2):
+//
+// DigestInfo ::= SEQUENCE {
+//     digestAlgorithm AlgorithmIdentifier,
+//     digest OCTET STRING
+// }
+//
+// The AlgorithmIdentifier is a SEQUENCE of the object identifier of the hash
+// function and its parameters, which are NULL for SHA-1 and SHA-2.
+
+use crate::{DerError, Value};
+
+pub const SHA1: &[u64] = &[1, 3, 14, 3, 2, 26];
+pub const SHA256: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DigestInfo {
+    pub algorithm: Vec<u64>,
+    pub digest: Vec<u8>,
+}
+
+impl DigestInfo {
+    pub fn new(algorithm: &[u64], digest: &[u8]) -> Self {
+        DigestInfo {
+            algorithm: algorithm.to_vec(),
+            digest: digest.to_vec(),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Sequence(vec![
+            Value::Sequence(vec![
+                Value::ObjectIdentifier(self.algorithm.clone()),
+                Value::Null,
+            ]),
+            Value::OctetString(self.digest.clone()),
+        ])
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self, DerError> {
+        match value.as_sequence()? {
+            [algorithm, digest] => match algorithm.as_sequence()? {
+                [oid, parameters] => {
+                    parameters.as_null()?;
+                    Ok(DigestInfo::new(
+                        oid.as_object_identifier()?,
+                        digest.as_octet_string()?,
+                    ))
+                }
+                _ => Err(DerError::UnexpectedStructure),
+            },
+            _ => Err(DerError::UnexpectedStructure),
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, DerError> {
+        self.to_value().encode()
+    }
+
+    pub fn decode(input: &[u8]) -> Result<Self, DerError> {
+        DigestInfo::from_value(&Value::decode(input)?)
+    }
+
+    // Like `decode`, but ignores anything after the DigestInfo. Verifiers
+    // should not use this, see challenge 42.
+    pub fn decode_prefix(input: &[u8]) -> Result<(Self, &[u8]), DerError> {
+        let (value, rest) = Value::decode_prefix(input)?;
+        Ok((DigestInfo::from_value(&value)?, rest))
+    }
+}
+
+#[test]
+fn test_digest_info() {
+    use serialize::from_hex;
+
+    // The DER prefixes listed in RFC 8017, section 9.2.
+    let digest = [0x5a; 20];
+    let mut expected = from_hex("3021300906052b0e03021a05000414").unwrap();
+    expected.extend_from_slice(&digest);
+    let digest_info = DigestInfo::new(SHA1, &digest);
+    assert_eq!(expected, digest_info.encode().unwrap());
+    assert_eq!(digest_info, DigestInfo::decode(&expected).unwrap());
+
+    let digest = [0xa5; 32];
+    let mut expected = from_hex("3031300d060960864801650304020105000420").unwrap();
+    expected.extend_from_slice(&digest);
+    assert_eq!(expected, DigestInfo::new(SHA256, &digest).encode().unwrap());
+
+    expected.push(0);
+    assert!(DigestInfo::decode(&expected).is_err());
+    assert_eq!(&[0][..], DigestInfo::decode_prefix(&expected).unwrap().1);
+
+    let missing_digest = Value::Sequence(vec![Value::Sequence(vec![
+        Value::ObjectIdentifier(SHA1.to_vec()),
+        Value::Null,
+    ])]);
+    assert_eq!(
+        Err(DerError::UnexpectedStructure),
+        DigestInfo::from_value(&missing_digest)
+    );
+    let missing_parameters = Value::Sequence(vec![
+        Value::Sequence(vec![Value::ObjectIdentifier(SHA1.to_vec())]),
+        Value::OctetString(digest.to_vec()),
+    ]);
+    assert_eq!(
+        Err(DerError::UnexpectedStructure),
+        DigestInfo::from_value(&missing_parameters)
+    );
+}
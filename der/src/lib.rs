@@ -0,0 +1,424 @@
+// Distinguished Encoding Rules (DER) for the ASN.1 types needed for keys and
+// signatures: INTEGER, BIT STRING, OCTET STRING, NULL, OBJECT IDENTIFIER and
+// SEQUENCE. Decoding rejects everything which is not the unique DER encoding
+// of a value, e.g. non-minimal lengths or integers with redundant leading
+// bytes.
+//
+// Only non-negative integers are supported since they map to `BigNumTrait`,
+// and only bit strings consisting of whole bytes.
+
+extern crate bignum;
+
+mod digest_info;
+
+pub use crate::digest_info::{DigestInfo, SHA1, SHA256};
+
+use bignum::BigNumTrait;
+
+use std::error;
+use std::fmt;
+
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+
+// The maximum nesting depth of SEQUENCEs accepted when decoding. Keys and
+// signatures need far less, and the limit bounds the recursion.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DerError {
+    UnexpectedTag { expected: u8, found: u8 },
+    UnsupportedTag { tag: u8 },
+    Truncated,
+    InvalidLength,
+    TrailingData,
+    InvalidInteger,
+    NegativeInteger,
+    InvalidBitString,
+    InvalidNull,
+    InvalidObjectIdentifier,
+    NestingTooDeep,
+    UnexpectedStructure,
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for DerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl fmt::Display for DerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DerError::UnexpectedTag { expected, found } => write!(
+                f,
+                "unexpected tag {:#04x}, expected {:#04x}",
+                found, expected
+            ),
+            DerError::UnsupportedTag { tag } => write!(f, "unsupported tag {:#04x}", tag),
+            DerError::Truncated => write!(f, "input is truncated"),
+            DerError::InvalidLength => write!(f, "invalid length"),
+            DerError::TrailingData => write!(f, "trailing data after value"),
+            DerError::InvalidInteger => write!(f, "integer not minimally encoded"),
+            DerError::NegativeInteger => write!(f, "negative integers are not supported"),
+            DerError::InvalidBitString => write!(f, "invalid bit string"),
+            DerError::InvalidNull => write!(f, "NULL with content"),
+            DerError::InvalidObjectIdentifier => write!(f, "invalid object identifier"),
+            DerError::NestingTooDeep => write!(f, "sequences nested deeper than {}", MAX_DEPTH),
+            DerError::UnexpectedStructure => write!(f, "unexpected number of sequence elements"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    // The big-endian bytes of a non-negative integer. Decoded integers have
+    // no leading zeros.
+    Integer(Vec<u8>),
+    BitString(Vec<u8>),
+    OctetString(Vec<u8>),
+    Null,
+    ObjectIdentifier(Vec<u64>),
+    Sequence(Vec<Value>),
+}
+
+impl Value {
+    pub fn integer<T: BigNumTrait>(n: &T) -> Self {
+        let bytes = n.to_bytes_be();
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        Value::Integer(bytes[start..].to_vec())
+    }
+
+    pub fn tag(&self) -> u8 {
+        match self {
+            Value::Integer(_) => INTEGER,
+            Value::BitString(_) => BIT_STRING,
+            Value::OctetString(_) => OCTET_STRING,
+            Value::Null => NULL,
+            Value::ObjectIdentifier(_) => OBJECT_IDENTIFIER,
+            Value::Sequence(_) => SEQUENCE,
+        }
+    }
+
+    // Fails only for object identifiers which cannot be encoded.
+    pub fn encode(&self) -> Result<Vec<u8>, DerError> {
+        let content = match self {
+            Value::Integer(bytes) => {
+                let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+                let bytes = &bytes[start..];
+                // A leading zero byte keeps the integer from being read as
+                // negative and represents zero itself.
+                let mut content = Vec::with_capacity(bytes.len() + 1);
+                if bytes.first().is_none_or(|&b| b & 0x80 != 0) {
+                    content.push(0);
+                }
+                content.extend_from_slice(bytes);
+                content
+            }
+            Value::BitString(bytes) => {
+                let mut content = Vec::with_capacity(bytes.len() + 1);
+                content.push(0); // The number of unused bits
+                content.extend_from_slice(bytes);
+                content
+            }
+            Value::OctetString(bytes) => bytes.clone(),
+            Value::Null => Vec::new(),
+            Value::ObjectIdentifier(components) => encode_object_identifier(components)?,
+            Value::Sequence(values) => values
+                .iter()
+                .map(|v| v.encode())
+                .collect::<Result<Vec<_>, _>>()?
+                .concat(),
+        };
+
+        let mut output = Vec::with_capacity(content.len() + 6);
+        output.push(self.tag());
+        encode_length(content.len(), &mut output);
+        output.extend_from_slice(&content);
+        Ok(output)
+    }
+
+    pub fn decode(input: &[u8]) -> Result<Self, DerError> {
+        let (value, rest) = Value::decode_prefix(input)?;
+        if !rest.is_empty() {
+            return Err(DerError::TrailingData);
+        }
+        Ok(value)
+    }
+
+    // Decodes the value at the start of input and returns it together with the
+    // remaining input.
+    pub fn decode_prefix(input: &[u8]) -> Result<(Self, &[u8]), DerError> {
+        Value::decode_nested(input, 0)
+    }
+
+    // depth is the number of enclosing SEQUENCEs.
+    fn decode_nested(input: &[u8], depth: usize) -> Result<(Self, &[u8]), DerError> {
+        let (&tag, rest) = input.split_first().ok_or(DerError::Truncated)?;
+        let (len, rest) = decode_length(rest)?;
+        if rest.len() < len {
+            return Err(DerError::Truncated);
+        }
+        let (content, rest) = rest.split_at(len);
+
+        let value = match tag {
+            INTEGER => Value::Integer(decode_integer(content)?),
+            BIT_STRING => match content.split_first() {
+                Some((0, bytes)) => Value::BitString(bytes.to_vec()),
+                _ => return Err(DerError::InvalidBitString),
+            },
+            OCTET_STRING => Value::OctetString(content.to_vec()),
+            NULL if content.is_empty() => Value::Null,
+            NULL => return Err(DerError::InvalidNull),
+            OBJECT_IDENTIFIER => Value::ObjectIdentifier(decode_object_identifier(content)?),
+            SEQUENCE if depth == MAX_DEPTH => return Err(DerError::NestingTooDeep),
+            SEQUENCE => {
+                let mut values = Vec::new();
+                let mut content = content;
+                while !content.is_empty() {
+                    let (value, rest) = Value::decode_nested(content, depth + 1)?;
+                    values.push(value);
+                    content = rest;
+                }
+                Value::Sequence(values)
+            }
+            tag => return Err(DerError::UnsupportedTag { tag }),
+        };
+        Ok((value, rest))
+    }
+
+    pub fn to_integer<T: BigNumTrait>(&self) -> Result<T, DerError> {
+        match self {
+            Value::Integer(bytes) => Ok(T::from_bytes_be(bytes)),
+            _ => Err(self.unexpected(INTEGER)),
+        }
+    }
+
+    pub fn as_bit_string(&self) -> Result<&[u8], DerError> {
+        match self {
+            Value::BitString(bytes) => Ok(bytes),
+            _ => Err(self.unexpected(BIT_STRING)),
+        }
+    }
+
+    pub fn as_octet_string(&self) -> Result<&[u8], DerError> {
+        match self {
+            Value::OctetString(bytes) => Ok(bytes),
+            _ => Err(self.unexpected(OCTET_STRING)),
+        }
+    }
+
+    pub fn as_null(&self) -> Result<(), DerError> {
+        match self {
+            Value::Null => Ok(()),
+            _ => Err(self.unexpected(NULL)),
+        }
+    }
+
+    pub fn as_object_identifier(&self) -> Result<&[u64], DerError> {
+        match self {
+            Value::ObjectIdentifier(components) => Ok(components),
+            _ => Err(self.unexpected(OBJECT_IDENTIFIER)),
+        }
+    }
+
+    pub fn as_sequence(&self) -> Result<&[Value], DerError> {
+        match self {
+            Value::Sequence(values) => Ok(values),
+            _ => Err(self.unexpected(SEQUENCE)),
+        }
+    }
+
+    fn unexpected(&self, expected: u8) -> DerError {
+        DerError::UnexpectedTag {
+            expected,
+            found: self.tag(),
+        }
+    }
+}
+
+// Lengths below 128 are encoded in a single byte. Longer lengths are encoded
+// as the number of length bytes with the high bit set, followed by the length
+// in big-endian.
+fn encode_length(len: usize, output: &mut Vec<u8>) {
+    if len < 0x80 {
+        output.push(len as u8);
+        return;
+    }
+
+    let bytes = len.to_be_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap(); // unwrap is ok, len is not zero
+    output.push(0x80 | (bytes.len() - start) as u8);
+    output.extend_from_slice(&bytes[start..]);
+}
+
+fn decode_length(input: &[u8]) -> Result<(usize, &[u8]), DerError> {
+    let (&first, rest) = input.split_first().ok_or(DerError::Truncated)?;
+    if first < 0x80 {
+        return Ok((first as usize, rest));
+    }
+
+    // 0x80 is the indefinite length, which is not allowed in DER.
+    let count = (first & 0x7f) as usize;
+    if count == 0 || count > std::mem::size_of::<usize>() {
+        return Err(DerError::InvalidLength);
+    }
+    if rest.len() < count {
+        return Err(DerError::Truncated);
+    }
+    let (bytes, rest) = rest.split_at(count);
+    if bytes[0] == 0 {
+        return Err(DerError::InvalidLength);
+    }
+    let len = bytes.iter().fold(0, |a, &b| a << 8 | b as usize);
+    if len < 0x80 {
+        return Err(DerError::InvalidLength);
+    }
+    Ok((len, rest))
+}
+
+fn decode_integer(content: &[u8]) -> Result<Vec<u8>, DerError> {
+    match content {
+        [] => Err(DerError::InvalidInteger),
+        [first, ..] if first & 0x80 != 0 => Err(DerError::NegativeInteger),
+        [0, second, ..] if second & 0x80 == 0 => Err(DerError::InvalidInteger),
+        [0, rest @ ..] => Ok(rest.to_vec()),
+        _ => Ok(content.to_vec()),
+    }
+}
+
+// The first two components are combined into a single one, so the first one
+// has to be at most 2 and the second one less than 40 unless the first one is
+// 2. Each component is encoded in base 128, most significant digit first, with
+// the high bit set on all but the last digit.
+fn encode_object_identifier(components: &[u64]) -> Result<Vec<u8>, DerError> {
+    let first = match components {
+        &[first @ 0..=1, second @ 0..=39, ..] | &[first @ 2, second, ..] => 40u64
+            .checked_mul(first)
+            .and_then(|n| n.checked_add(second))
+            .ok_or(DerError::InvalidObjectIdentifier)?,
+        _ => return Err(DerError::InvalidObjectIdentifier),
+    };
+
+    let mut output = Vec::new();
+    for &component in std::iter::once(&first).chain(components[2..].iter()) {
+        let digits = (64 - component.leading_zeros()).div_ceil(7).max(1);
+        for i in (0..digits).rev() {
+            let digit = (component >> (7 * i)) as u8 & 0x7f;
+            output.push(if i > 0 { digit | 0x80 } else { digit });
+        }
+    }
+    Ok(output)
+}
+
+fn decode_object_identifier(content: &[u8]) -> Result<Vec<u64>, DerError> {
+    let mut components = Vec::new();
+    let mut component = 0u64;
+    let mut digits = 0;
+    for &b in content {
+        // A leading 0x80 digit is not minimal.
+        if (digits == 0 && b == 0x80) || component.leading_zeros() < 7 {
+            return Err(DerError::InvalidObjectIdentifier);
+        }
+        component = component << 7 | u64::from(b & 0x7f);
+        digits += 1;
+        if b & 0x80 == 0 {
+            if components.is_empty() {
+                let first = (component / 40).min(2);
+                components.push(first);
+                components.push(component - 40 * first);
+            } else {
+                components.push(component);
+            }
+            component = 0;
+            digits = 0;
+        }
+    }
+
+    if digits > 0 || components.is_empty() {
+        return Err(DerError::InvalidObjectIdentifier);
+    }
+    Ok(components)
+}
+
+#[test]
+fn test_der() {
+    use bignum::OpensslBigNum as BigNum;
+    use serialize::from_hex;
+
+    for &(n, encoding) in &[
+        (0, "020100"),
+        (127, "02017f"),
+        (128, "02020080"),
+        (256, "02020100"),
+    ] {
+        let value = Value::integer(&BigNum::from_u32(n));
+        assert_eq!(from_hex(encoding).unwrap(), value.encode().unwrap());
+        let decoded = Value::decode(&value.encode().unwrap()).unwrap();
+        assert_eq!(BigNum::from_u32(n), decoded.to_integer::<BigNum>().unwrap());
+    }
+    assert_eq!(
+        Err(DerError::InvalidInteger),
+        Value::decode(&from_hex("0202007f").unwrap())
+    );
+    assert_eq!(
+        Err(DerError::NegativeInteger),
+        Value::decode(&from_hex("020180").unwrap())
+    );
+
+    // 1.2.840.113549.1.1.1 is rsaEncryption.
+    let oid = Value::ObjectIdentifier(vec![1, 2, 840, 113_549, 1, 1, 1]);
+    let encoding = from_hex("06092a864886f70d010101").unwrap();
+    assert_eq!(encoding, oid.encode().unwrap());
+    assert_eq!(oid, Value::decode(&encoding).unwrap());
+    for components in &[vec![1], vec![3, 1], vec![1, 40], vec![2, u64::MAX]] {
+        assert_eq!(
+            Err(DerError::InvalidObjectIdentifier),
+            Value::ObjectIdentifier(components.clone()).encode()
+        );
+    }
+
+    let long = Value::OctetString(vec![0xab; 200]);
+    let encoding = long.encode().unwrap();
+    assert_eq!(&from_hex("0481c8abab").unwrap()[..], &encoding[..5]);
+    assert_eq!(long, Value::decode(&encoding).unwrap());
+    assert_eq!(
+        Err(DerError::InvalidLength),
+        Value::decode(&from_hex("04810100").unwrap())
+    );
+
+    let sequence = Value::Sequence(vec![
+        Value::Null,
+        Value::BitString(vec![1, 2]),
+        Value::Sequence(vec![]),
+    ]);
+    let encoding = from_hex("3009050003030001023000").unwrap();
+    assert_eq!(encoding, sequence.encode().unwrap());
+    assert_eq!(sequence, Value::decode(&encoding).unwrap());
+    assert_eq!(
+        Err(DerError::TrailingData),
+        Value::decode(&from_hex("050000").unwrap())
+    );
+    assert_eq!(
+        Err(DerError::Truncated),
+        Value::decode(&encoding[..encoding.len() - 1])
+    );
+
+    let nested = |depth: usize| {
+        (1..depth)
+            .fold(Value::Sequence(vec![]), |v, _| Value::Sequence(vec![v]))
+            .encode()
+            .unwrap()
+    };
+    assert!(Value::decode(&nested(MAX_DEPTH)).is_ok());
+    assert_eq!(
+        Err(DerError::NestingTooDeep),
+        Value::decode(&nested(MAX_DEPTH + 1))
+    );
+}
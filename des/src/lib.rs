@@ -0,0 +1,362 @@
+// DES and Triple DES as specified in FIPS 46-3 and NIST SP 800-67. Both have
+// a block size of 8 bytes. Triple DES is used in the EDE (encrypt, decrypt,
+// encrypt) configuration with either two (EDE2) or three (EDE3) keys.
+//
+// The parity bits of the keys (the least significant bit of each byte) are
+// ignored, as in most implementations.
+
+extern crate cipher;
+extern crate rand;
+
+mod tables;
+
+use cipher::{BlockCipher, ModeError};
+use rand::Rng;
+use tables::{E, FP, IP, P, PC1, PC2, SBOXES, SHIFTS};
+
+use std::error;
+use std::fmt;
+
+pub const BLOCK_SIZE: usize = 8;
+pub const KEY_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum DesError {
+    InvalidKeyLength { len: usize },
+    Mode(ModeError),
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for DesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DesError::Mode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesError::InvalidKeyLength { len } => {
+                write!(f, "key length {} is not one of 8, 16 or 24", len)
+            }
+            DesError::Mode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ModeError> for DesError {
+    fn from(err: ModeError) -> Self {
+        DesError::Mode(err)
+    }
+}
+
+pub struct Des {
+    subkeys: [u64; 16],
+}
+
+impl Des {
+    pub fn new(key: &[u8]) -> Result<Self, DesError> {
+        if key.len() != KEY_SIZE {
+            return Err(DesError::InvalidKeyLength { len: key.len() });
+        }
+
+        let key = permute(read_u64(key), 64, &PC1);
+        let mut c = (key >> 28) as u32;
+        let mut d = (key & 0x0fff_ffff) as u32;
+        let mut subkeys = [0; 16];
+        for (subkey, &shift) in subkeys.iter_mut().zip(SHIFTS.iter()) {
+            c = rotate_28(c, shift);
+            d = rotate_28(d, shift);
+            *subkey = permute((u64::from(c) << 28) | u64::from(d), 56, &PC2);
+        }
+        Ok(Des { subkeys })
+    }
+
+    pub fn encrypt_u64(&self, block: u64) -> u64 {
+        feistel(block, self.subkeys.iter())
+    }
+
+    // Decryption applies the subkeys in reverse order.
+    pub fn decrypt_u64(&self, block: u64) -> u64 {
+        feistel(block, self.subkeys.iter().rev())
+    }
+}
+
+impl BlockCipher for Des {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let output = self.encrypt_u64(read_u64(block));
+        block.copy_from_slice(&output.to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let output = self.decrypt_u64(read_u64(block));
+        block.copy_from_slice(&output.to_be_bytes());
+    }
+}
+
+pub struct TripleDes {
+    k1: Des,
+    k2: Des,
+    k3: Des,
+}
+
+impl TripleDes {
+    // A 16 byte key selects EDE2, where the third key is equal to the first
+    // one, and a 24 byte key selects EDE3. Degenerate keys are accepted for
+    // compatibility with single DES, see is_degenerate_key.
+    pub fn new(key: &[u8]) -> Result<Self, DesError> {
+        let [k1, k2, k3] = split_key(key)?;
+        Ok(TripleDes {
+            k1: Des::new(k1)?,
+            k2: Des::new(k2)?,
+            k3: Des::new(k3)?,
+        })
+    }
+}
+
+fn split_key(key: &[u8]) -> Result<[&[u8]; 3], DesError> {
+    match key.len() {
+        16 => Ok([&key[..8], &key[8..], &key[..8]]),
+        24 => Ok([&key[..8], &key[8..16], &key[16..]]),
+        len => Err(DesError::InvalidKeyLength { len }),
+    }
+}
+
+impl BlockCipher for TripleDes {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let x = read_u64(block);
+        let output = self
+            .k3
+            .encrypt_u64(self.k2.decrypt_u64(self.k1.encrypt_u64(x)));
+        block.copy_from_slice(&output.to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let x = read_u64(block);
+        let output = self
+            .k1
+            .decrypt_u64(self.k2.encrypt_u64(self.k3.decrypt_u64(x)));
+        block.copy_from_slice(&output.to_be_bytes());
+    }
+}
+
+// ECB and CBC with PKCS#7 padding. The key length selects the cipher: 8 bytes
+// for DES, 16 bytes for EDE2 and 24 bytes for EDE3.
+pub fn encrypt_des_ecb(input: &[u8], key: &[u8]) -> Result<Vec<u8>, DesError> {
+    Ok(cipher::encrypt_ecb(&*new_cipher(key)?, input)?)
+}
+
+pub fn decrypt_des_ecb(input: &[u8], key: &[u8]) -> Result<Vec<u8>, DesError> {
+    Ok(cipher::decrypt_ecb(&*new_cipher(key)?, input)?)
+}
+
+pub fn encrypt_des_cbc(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, DesError> {
+    Ok(cipher::encrypt_cbc(&*new_cipher(key)?, input, iv)?)
+}
+
+pub fn decrypt_des_cbc(input: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, DesError> {
+    Ok(cipher::decrypt_cbc(&*new_cipher(key)?, input, iv)?)
+}
+
+fn new_cipher(key: &[u8]) -> Result<Box<dyn BlockCipher>, DesError> {
+    if key.len() == KEY_SIZE {
+        Ok(Box::new(Des::new(key)?))
+    } else {
+        Ok(Box::new(TripleDes::new(key)?))
+    }
+}
+
+// The four weak keys, for which encryption is an involution.
+static WEAK_KEYS: [u64; 4] = [
+    0x0101_0101_0101_0101,
+    0xfefe_fefe_fefe_fefe,
+    0xe0e0_e0e0_f1f1_f1f1,
+    0x1f1f_1f1f_0e0e_0e0e,
+];
+
+// The six pairs of semi-weak keys. Encryption with one key of a pair is
+// decryption with the other one.
+static SEMI_WEAK_KEYS: [u64; 12] = [
+    0x01fe_01fe_01fe_01fe,
+    0xfe01_fe01_fe01_fe01,
+    0x1fe0_1fe0_0ef1_0ef1,
+    0xe01f_e01f_f10e_f10e,
+    0x01e0_01e0_01f1_01f1,
+    0xe001_e001_f101_f101,
+    0x1ffe_1ffe_0efe_0efe,
+    0xfe1f_fe1f_fe0e_fe0e,
+    0x011f_011f_010e_010e,
+    0x1f01_1f01_0e01_0e01,
+    0xe0fe_e0fe_f1fe_f1fe,
+    0xfee0_fee0_fef1_fef1,
+];
+
+const PARITY_MASK: u64 = 0xfefe_fefe_fefe_fefe;
+
+pub fn is_weak_key(key: &[u8]) -> bool {
+    key.len() == KEY_SIZE && contains_key(&WEAK_KEYS, key)
+}
+
+pub fn is_semi_weak_key(key: &[u8]) -> bool {
+    key.len() == KEY_SIZE && contains_key(&SEMI_WEAK_KEYS, key)
+}
+
+// A Triple DES key degenerates to single DES if two adjacent keys are equal,
+// because the first two or the last two operations cancel out.
+pub fn is_degenerate_key(key: &[u8]) -> bool {
+    match split_key(key) {
+        Ok([k1, k2, k3]) => keys_equal(k1, k2) || keys_equal(k2, k3),
+        Err(_) => false,
+    }
+}
+
+fn contains_key(keys: &[u64], key: &[u8]) -> bool {
+    let key = read_u64(key) & PARITY_MASK;
+    keys.iter().any(|&k| k & PARITY_MASK == key)
+}
+
+fn keys_equal(k1: &[u8], k2: &[u8]) -> bool {
+    read_u64(k1) & PARITY_MASK == read_u64(k2) & PARITY_MASK
+}
+
+// Returns a random key of the given length which is neither weak nor
+// semi-weak and, for Triple DES, not degenerate.
+pub fn random_key(len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    loop {
+        let key: Vec<u8> = rng.gen_iter().take(len).collect();
+        if key
+            .chunks(KEY_SIZE)
+            .all(|k| !is_weak_key(k) && !is_semi_weak_key(k))
+            && !is_degenerate_key(&key)
+        {
+            return key;
+        }
+    }
+}
+
+fn feistel<'a, I>(block: u64, subkeys: I) -> u64
+where
+    I: Iterator<Item = &'a u64>,
+{
+    let block = permute(block, 64, &IP);
+    let mut l = (block >> 32) as u32;
+    let mut r = block as u32;
+    for &subkey in subkeys {
+        let next = l ^ f(r, subkey);
+        l = r;
+        r = next;
+    }
+    // The halves are swapped after the last round.
+    permute((u64::from(r) << 32) | u64::from(l), 64, &FP)
+}
+
+fn f(r: u32, subkey: u64) -> u32 {
+    let x = permute(u64::from(r), 32, &E) ^ subkey;
+    let mut output = 0u32;
+    for (i, sbox) in SBOXES.iter().enumerate() {
+        let chunk = ((x >> (42 - 6 * i)) & 0x3f) as usize;
+        let row = ((chunk & 0x20) >> 4) | (chunk & 1);
+        let column = (chunk >> 1) & 0xf;
+        output = (output << 4) | u32::from(sbox[16 * row + column]);
+    }
+    permute(u64::from(output), 32, &P) as u32
+}
+
+// The tables number the bits of the input from 1 (most significant) to
+// input_bits (least significant), as in the standard.
+fn permute(input: u64, input_bits: u32, table: &[u8]) -> u64 {
+    table.iter().fold(0, |acc, &position| {
+        (acc << 1) | ((input >> (input_bits - u32::from(position))) & 1)
+    })
+}
+
+fn rotate_28(x: u32, shift: u32) -> u32 {
+    ((x << shift) | (x >> (28 - shift))) & 0x0fff_ffff
+}
+
+fn read_u64(u: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(u);
+    u64::from_be_bytes(bytes)
+}
+
+#[test]
+fn test_des() {
+    use serialize::from_hex;
+
+    let key = from_hex("133457799BBCDFF1").unwrap();
+    let input = from_hex("0123456789ABCDEF").unwrap();
+    let mut block = input.clone();
+    let des = Des::new(&key).unwrap();
+    des.encrypt_block(&mut block);
+    assert_eq!(from_hex("85E813540F0AB405").unwrap(), block);
+    des.decrypt_block(&mut block);
+    assert_eq!(input, block);
+
+    // Weak keys are self-inverse.
+    let weak_key = from_hex("FEFEFEFEFEFEFEFE").unwrap();
+    assert!(is_weak_key(&weak_key));
+    assert!(is_weak_key(&from_hex("FFFFFFFFFFFFFFFF").unwrap()));
+    assert!(!is_weak_key(&key));
+    assert!(is_semi_weak_key(&from_hex("E0FEE0FEF1FEF1FE").unwrap()));
+    let weak = Des::new(&weak_key).unwrap();
+    weak.encrypt_block(&mut block);
+    weak.encrypt_block(&mut block);
+    assert_eq!(input, block);
+}
+
+#[test]
+fn test_triple_des() {
+    use serialize::from_hex;
+
+    let key = from_hex("0123456789abcdef23456789abcdef01456789abcdef0123").unwrap();
+    let ciphertext = encrypt_des_ecb(b"The quick brown fox jump", &key).unwrap();
+    assert_eq!(
+        from_hex("1ccf23869d09333ecce21c8112256fe668d5c05dd9b6b900").unwrap(),
+        &ciphertext[..24]
+    );
+    assert_eq!(
+        b"The quick brown fox jump".as_ref(),
+        &decrypt_des_ecb(&ciphertext, &key).unwrap()[..]
+    );
+
+    let key = from_hex("0123456789abcdeffedcba9876543210").unwrap();
+    let iv = from_hex("1234567890abcdef").unwrap();
+    let ciphertext = encrypt_des_cbc(b"Now is the time for all ", &key, &iv).unwrap();
+    assert_eq!(
+        from_hex("f85d4ab92066789e1d0430671f28ae7ab9627d35385d2e24").unwrap(),
+        &ciphertext[..24]
+    );
+    assert_eq!(
+        b"Now is the time for all ".as_ref(),
+        &decrypt_des_cbc(&ciphertext, &key, &iv).unwrap()[..]
+    );
+
+    // EDE with K1 = K2 = K3 is single DES. The keys differ in the parity bit.
+    let key = from_hex("133457799BBCDFF1133457799BBCDFF0133457799BBCDFF1").unwrap();
+    assert!(is_degenerate_key(&key));
+    assert!(is_degenerate_key(&key[..16]));
+    assert!(!is_degenerate_key(&key[..8]));
+    let mut block = from_hex("0123456789ABCDEF").unwrap();
+    TripleDes::new(&key).unwrap().encrypt_block(&mut block);
+    assert_eq!(from_hex("85E813540F0AB405").unwrap(), block);
+    assert_eq!(
+        encrypt_des_cbc(b"Now is the time", &key[..8], &iv).unwrap(),
+        encrypt_des_cbc(b"Now is the time", &key, &iv).unwrap()
+    );
+    assert!(TripleDes::new(&[0; 8]).is_err());
+    assert!(!is_degenerate_key(&random_key(24)));
+    assert_eq!(16, random_key(16).len());
+}
@@ -0,0 +1,132 @@
+// The RC4 stream cipher. The key scheduling algorithm (KSA) initializes the
+// state from the key, after which the pseudo-random generation algorithm
+// (PRGA) produces one keystream byte at a time.
+
+extern crate cipher;
+extern crate rand;
+extern crate xor;
+
+pub mod stats;
+
+use cipher::StreamCipher;
+use xor::XOR;
+
+use std::error;
+use std::fmt;
+
+pub const MAX_KEY_SIZE: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum Rc4Error {
+    InvalidKeyLength { len: usize },
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for Rc4Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl fmt::Display for Rc4Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rc4Error::InvalidKeyLength { len } => write!(
+                f,
+                "key length {} is not between 1 and {}",
+                len, MAX_KEY_SIZE
+            ),
+        }
+    }
+}
+
+// The keystream generator. Iterating over it yields the keystream bytes.
+#[derive(Clone)]
+pub struct Rc4 {
+    s: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    pub fn new(key: &[u8]) -> Result<Self, Rc4Error> {
+        if key.is_empty() || key.len() > MAX_KEY_SIZE {
+            return Err(Rc4Error::InvalidKeyLength { len: key.len() });
+        }
+
+        let mut s = [0; 256];
+        for (i, x) in s.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+            s.swap(i, j as usize);
+        }
+        Ok(Rc4 { s, i: 0, j: 0 })
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.s[self.i as usize]);
+        self.s.swap(self.i as usize, self.j as usize);
+        self.s[self.s[self.i as usize].wrapping_add(self.s[self.j as usize]) as usize]
+    }
+
+    pub fn fill_keystream(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = self.next_byte();
+        }
+    }
+
+    pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b ^= self.next_byte();
+        }
+    }
+}
+
+impl StreamCipher for Rc4 {
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        Rc4::apply_keystream(self, buf)
+    }
+}
+
+impl Iterator for Rc4 {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        Some(self.next_byte())
+    }
+}
+
+// Encrypts or decrypts input under a fresh keystream for key.
+pub fn rc4(input: &[u8], key: &[u8]) -> Result<Vec<u8>, Rc4Error> {
+    let mut keystream = vec![0; input.len()];
+    Rc4::new(key)?.fill_keystream(&mut keystream);
+    Ok(input.xor_truncating(&keystream))
+}
+
+#[test]
+fn test_rc4() {
+    use serialize::from_hex;
+
+    assert_eq!(
+        from_hex("bbf316e8d940af0ad3").unwrap(),
+        rc4(b"Plaintext", b"Key").unwrap()
+    );
+
+    // RFC 6229, 40-bit key, offset 0
+    assert_eq!(
+        from_hex("b2396305f03dc027ccc3524a0a1118a86982944f18fc82d589c403a47a0d0919").unwrap(),
+        Rc4::new(&[1, 2, 3, 4, 5])
+            .unwrap()
+            .take(32)
+            .collect::<Vec<u8>>()
+    );
+
+    assert!(Rc4::new(&[]).is_err());
+    assert!(Rc4::new(&[0; MAX_KEY_SIZE + 1]).is_err());
+}
@@ -0,0 +1,130 @@
+// Keystream bias statistics. The same cleartext is encrypted under many random
+// keys and the ciphertext bytes at each position are counted. Since the
+// keystream is biased at some positions (e.g. the second byte towards 0 and
+// the 16th and 32nd bytes towards 240 and 224), the most frequent ciphertext
+// bytes reveal the cleartext at those positions.
+
+use crate::Rc4;
+use rand::Rng;
+use xor::XOR;
+
+use std::thread;
+
+// Byte counts for each position of a ciphertext. Positions are zero-based,
+// so the 16th keystream byte is at position 15.
+#[derive(Clone)]
+pub struct FrequencyTable {
+    counts: Vec<[u64; 256]>,
+    samples: u64,
+}
+
+impl FrequencyTable {
+    pub fn new(positions: usize) -> Self {
+        FrequencyTable {
+            counts: vec![[0; 256]; positions],
+            samples: 0,
+        }
+    }
+
+    pub fn positions(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn count(&self, position: usize, byte: u8) -> u64 {
+        self.counts[position][byte as usize]
+    }
+
+    pub fn counts(&self, position: usize) -> &[u64; 256] {
+        &self.counts[position]
+    }
+
+    // Returns the most frequent byte at the position. Ties are resolved in
+    // favour of the smaller byte.
+    pub fn most_frequent(&self, position: usize) -> u8 {
+        let counts = &self.counts[position];
+        (0..=255u8)
+            .max_by_key(|&b| (counts[b as usize], 255 - b))
+            .unwrap() // unwrap is ok
+    }
+
+    pub fn add_sample(&mut self, sample: &[u8]) {
+        for (counts, &b) in self.counts.iter_mut().zip(sample.iter()) {
+            counts[b as usize] += 1;
+        }
+        self.samples += 1;
+    }
+
+    // Adds the counts of other, which needs to have the same number of
+    // positions.
+    pub fn merge(&mut self, other: &FrequencyTable) {
+        assert_eq!(self.positions(), other.positions());
+        for (counts, other_counts) in self.counts.iter_mut().zip(other.counts.iter()) {
+            for (x, y) in counts.iter_mut().zip(other_counts.iter()) {
+                *x += y;
+            }
+        }
+        self.samples += other.samples;
+    }
+}
+
+// Encrypts cleartext under the given number of random keys of length key_len
+// and counts the ciphertext bytes. The work is split across all available
+// threads.
+pub fn ciphertext_frequencies(cleartext: &[u8], key_len: usize, samples: u64) -> FrequencyTable {
+    assert!((1..=crate::MAX_KEY_SIZE).contains(&key_len));
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let tables: Vec<FrequencyTable> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let thread_samples = samples / threads + u64::from(i < samples % threads);
+                s.spawn(move || collect_samples(cleartext, key_len, thread_samples))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap()) // unwrap is ok
+            .collect()
+    });
+
+    let mut table = FrequencyTable::new(cleartext.len());
+    for t in &tables {
+        table.merge(t);
+    }
+    table
+}
+
+// The keystream itself, that is the ciphertext frequencies of an all zero
+// cleartext.
+pub fn keystream_frequencies(positions: usize, key_len: usize, samples: u64) -> FrequencyTable {
+    ciphertext_frequencies(&vec![0; positions], key_len, samples)
+}
+
+fn collect_samples(cleartext: &[u8], key_len: usize, samples: u64) -> FrequencyTable {
+    let mut rng = rand::thread_rng();
+    let mut table = FrequencyTable::new(cleartext.len());
+    let mut key = vec![0; key_len];
+    let mut keystream = vec![0; cleartext.len()];
+    let mut ciphertext = cleartext.to_vec();
+    for _ in 0..samples {
+        rng.fill_bytes(&mut key);
+        Rc4::new(&key).unwrap().fill_keystream(&mut keystream); // unwrap is ok
+        ciphertext.copy_from_slice(cleartext);
+        ciphertext.xor_exact_inplace(&keystream).unwrap(); // unwrap is ok
+        table.add_sample(&ciphertext);
+    }
+    table
+}
+
+#[test]
+fn test_second_byte_bias() {
+    // The second keystream byte is 0 with probability 2/256 (Mantin-Shamir).
+    let cleartext = b"ICE ICE BABY";
+    let table = ciphertext_frequencies(cleartext, 16, 1 << 16);
+    assert_eq!(1 << 16, table.samples());
+    assert_eq!(cleartext[1], table.most_frequent(1));
+}
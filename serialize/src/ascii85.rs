@@ -0,0 +1,180 @@
+// Base 85 encodings, which encode groups of four bytes as five characters.
+// The last group may be partial, in which case it is padded with zero bytes
+// and only the first characters are kept. This is standard for Ascii85; for
+// Z85 it is a common extension, the specification only allows whole groups.
+//
+// Ascii85 abbreviates whole groups of zero bytes as 'z'. Its lenient decoding
+// additionally strips the "<~" and "~>" delimiters used by Adobe.
+
+use crate::{DecodeError, Encoding, Result};
+
+const GROUP_SIZE: usize = 4;
+const ENCODED_GROUP_SIZE: usize = 5;
+const ZERO_GROUP: char = 'z';
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ascii85 {
+    alphabet: &'static [u8; 85],
+    adobe: bool,
+}
+
+static ASCII85_ALPHABET: &[u8; 85] =
+    b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstu";
+static Z85_ALPHABET: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+pub const ASCII85: Ascii85 = Ascii85 {
+    alphabet: ASCII85_ALPHABET,
+    adobe: true,
+};
+pub const Z85: Ascii85 = Ascii85 {
+    alphabet: Z85_ALPHABET,
+    adobe: false,
+};
+
+impl Ascii85 {
+    fn decoding_table(&self) -> [Option<u8>; 256] {
+        let mut table = [None; 256];
+        for (i, &c) in self.alphabet.iter().enumerate() {
+            table[c as usize] = Some(i as u8);
+        }
+        table
+    }
+
+    fn decode_with(&self, input: &str, lenient: bool) -> Result<Vec<u8>> {
+        // Offsets in errors refer to the original input.
+        let mut start = 0;
+        let mut input = input;
+        if lenient && self.adobe {
+            let trimmed = input.trim_start();
+            let trimmed = trimmed.strip_prefix("<~").unwrap_or(trimmed);
+            start = input.len() - trimmed.len();
+            input = trimmed.trim_end();
+            input = input.strip_suffix("~>").unwrap_or(input);
+        }
+
+        let table = self.decoding_table();
+        let mut output = Vec::with_capacity(GROUP_SIZE * input.len() / ENCODED_GROUP_SIZE);
+        let mut group = Vec::with_capacity(ENCODED_GROUP_SIZE);
+        let mut group_offset = 0;
+        let mut digits = 0;
+        for (offset, c) in input.char_indices() {
+            let offset = start + offset;
+            if lenient && c.is_ascii_whitespace() {
+                continue;
+            }
+            if group.is_empty() {
+                group_offset = offset;
+            }
+            if self.adobe && c == ZERO_GROUP && group.is_empty() {
+                output.extend_from_slice(&[0; GROUP_SIZE]);
+                digits += 1;
+                continue;
+            }
+
+            match table.get(c as usize) {
+                Some(&Some(value)) => group.push(value),
+                _ => {
+                    return Err(DecodeError::InvalidCharacter {
+                        character: c,
+                        offset,
+                    })
+                }
+            }
+            if group.len() == ENCODED_GROUP_SIZE {
+                output.extend_from_slice(&decode_group(&group, group_offset)?);
+                group.clear();
+            }
+            digits += 1;
+        }
+
+        match group.len() {
+            0 => {}
+            1 => return Err(DecodeError::InvalidLength { length: digits }),
+            len => {
+                // Padding with the largest digit rounds the value up, so that
+                // truncating it gives back the original bytes.
+                group.resize(ENCODED_GROUP_SIZE, 84);
+                output.extend_from_slice(&decode_group(&group, group_offset)?[..len - 1]);
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn decode_group(digits: &[u8], offset: usize) -> Result<[u8; GROUP_SIZE]> {
+    let value = digits.iter().fold(0u64, |a, &d| 85 * a + u64::from(d));
+    if value > u64::from(u32::MAX) {
+        return Err(DecodeError::Overflow { offset });
+    }
+    Ok((value as u32).to_be_bytes())
+}
+
+impl Encoding for Ascii85 {
+    fn encode(&self, input: &[u8]) -> String {
+        let mut output =
+            String::with_capacity(input.len().div_ceil(GROUP_SIZE) * ENCODED_GROUP_SIZE);
+        for chunk in input.chunks(GROUP_SIZE) {
+            let mut group = [0; GROUP_SIZE];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let mut value = u32::from_be_bytes(group);
+            if self.adobe && value == 0 && chunk.len() == GROUP_SIZE {
+                output.push(ZERO_GROUP);
+                continue;
+            }
+
+            let mut digits = [0; ENCODED_GROUP_SIZE];
+            for d in digits.iter_mut().rev() {
+                *d = self.alphabet[(value % 85) as usize];
+                value /= 85;
+            }
+            output.extend(digits[..chunk.len() + 1].iter().map(|&d| d as char));
+        }
+        output
+    }
+
+    // Different inputs can decode to the same bytes, e.g. "!!!!!" and "z" or
+    // partial groups with different last digits. Only the encoding produced
+    // by `encode` is accepted.
+    fn decode(&self, input: &str) -> Result<Vec<u8>> {
+        let output = self.decode_with(input, false)?;
+        if self.encode(&output) != input {
+            return Err(DecodeError::NonCanonical);
+        }
+        Ok(output)
+    }
+
+    fn decode_lenient(&self, input: &str) -> Result<Vec<u8>> {
+        self.decode_with(input, true)
+    }
+}
+
+#[test]
+fn test_ascii85() {
+    let input = b"Man is";
+    assert_eq!("9jqo^Bla", ASCII85.encode(input));
+    assert_eq!(input, &ASCII85.decode("9jqo^Bla").unwrap()[..]);
+    assert_eq!(
+        input,
+        &ASCII85.decode_lenient("<~9jqo^\nBla~>").unwrap()[..]
+    );
+    assert!(ASCII85.decode("<~9jqo^Bla~>").is_err());
+
+    let input = b"\0\0\0\0abc";
+    assert_eq!("z@:E^", ASCII85.encode(input));
+    assert_eq!(input, &ASCII85.decode_lenient("!!!!!@:E^").unwrap()[..]);
+    assert!(ASCII85.decode("!!!!!@:E^").is_err());
+    assert!(ASCII85.decode("@:zE^").is_err());
+
+    let input = [0xff; 3];
+    assert_eq!("s8W*", ASCII85.encode(&input));
+    assert_eq!(input, &ASCII85.decode("s8W*").unwrap()[..]);
+    assert!(ASCII85.decode("s8W+").is_err());
+    assert!(ASCII85.decode("uuuuu").is_err());
+
+    // The example from the Z85 specification.
+    let input = [0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+    assert_eq!("HelloWorld", Z85.encode(&input));
+    assert_eq!(input, &Z85.decode("HelloWorld").unwrap()[..]);
+    assert_eq!("00000", Z85.encode(&[0; 4]));
+}
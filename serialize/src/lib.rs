@@ -1,96 +1,199 @@
-use std::char;
+mod ascii85;
+mod pem;
+mod rfc4648;
+mod stream;
+
+pub use crate::ascii85::{Ascii85, ASCII85, Z85};
+pub use crate::pem::{parse_pem, Pem};
+pub use crate::rfc4648::{
+    Rfc4648, BASE32, BASE32_NOPAD, BASE64, BASE64URL, BASE64URL_NOPAD, BASE64_NOPAD, HEX,
+};
+pub use crate::stream::{Base64Decoder, Base64Encoder, Decoder, Encoder, HexDecoder, HexEncoder};
+
+use std::error;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::path::Path;
 
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+type Result<T> = std::result::Result<T, DecodeError>;
+
+// Offsets are byte offsets into the decoded string. The file functions and
+// the PEM parser wrap errors in `Line`, with line numbers starting at 1 and
+// offsets relative to the start of the line.
+#[derive(Debug)]
+pub enum DecodeError {
+    InvalidCharacter {
+        character: char,
+        offset: usize,
+    },
+    InvalidLength {
+        length: usize,
+    },
+    NonZeroPadding,
+    Truncated,
+    Overflow {
+        offset: usize,
+    },
+    NonCanonical,
+    MissingPemEnd {
+        label: String,
+    },
+    MismatchedPemEnd {
+        expected: String,
+        found: String,
+    },
+    InvalidPemHeader,
+    Io(io::Error),
+    Line {
+        line: usize,
+        error: Box<DecodeError>,
+    },
+}
 
-pub trait Serialize {
-    fn to_base64(&self) -> String;
-    fn to_hex(&self) -> String;
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
 }
 
-impl Serialize for [u8] {
-    fn to_base64(&self) -> String {
-        let mut base64 = String::with_capacity(4 * self.len() / 3);
-        for block in self.chunks(3) {
-            block_to_base64(block, &mut base64);
+// This is important for other errors to wrap this one.
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            DecodeError::Line { error, .. } => Some(error.as_ref()),
+            _ => None,
         }
+    }
+}
 
-        if self.len() % 3 >= 1 {
-            base64.pop();
-            if self.len() % 3 == 1 {
-                base64.pop();
-                base64.push('=');
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::InvalidCharacter { character, offset } => {
+                write!(f, "invalid character {:?} at offset {}", character, offset)
+            }
+            DecodeError::InvalidLength { length } => write!(f, "invalid input length {}", length),
+            DecodeError::NonZeroPadding => write!(f, "padding bits are not zero"),
+            DecodeError::Truncated => write!(f, "input is truncated"),
+            DecodeError::Overflow { offset } => {
+                write!(f, "group at offset {} is out of range", offset)
             }
-            base64.push('=');
+            DecodeError::NonCanonical => write!(f, "input is not canonically encoded"),
+            DecodeError::MissingPemEnd { label } => write!(f, "missing end of {} block", label),
+            DecodeError::MismatchedPemEnd { expected, found } => {
+                write!(f, "end of {} block inside {} block", found, expected)
+            }
+            DecodeError::InvalidPemHeader => write!(f, "invalid PEM header"),
+            DecodeError::Io(err) => write!(f, "I/O error: {}", err),
+            DecodeError::Line { line, error } => write!(f, "line {}: {}", line, error),
         }
-
-        base64
     }
+}
 
-    fn to_hex(&self) -> String {
-        let mut u4 = Vec::with_capacity(2 * self.len());
-        for u in self {
-            u4.push(u >> 4);
-            u4.push(u & 0xf);
+impl DecodeError {
+    pub(crate) fn map_offset(self, f: impl FnOnce(usize) -> usize) -> Self {
+        match self {
+            DecodeError::InvalidCharacter { character, offset } => DecodeError::InvalidCharacter {
+                character,
+                offset: f(offset),
+            },
+            DecodeError::Overflow { offset } => DecodeError::Overflow { offset: f(offset) },
+            err => err,
         }
-        u4.iter()
-            .map(|&u| char::from_digit(u32::from(u), 16).unwrap())
-            .collect()
     }
-}
 
-pub fn from_base64(s: &str) -> Result<Vec<u8>> {
-    if s.len() % 4 != 0 {
-        return Err("input length needs to be multiple of 4".into());
+    fn offset(&self) -> Option<usize> {
+        match self {
+            DecodeError::InvalidCharacter { offset, .. } | DecodeError::Overflow { offset } => {
+                Some(*offset)
+            }
+            _ => None,
+        }
     }
 
-    let mut n = s.len();
-    if s.as_bytes()[n - 1] == b'=' {
-        if s.as_bytes()[n - 2] == b'=' {
-            n -= 1;
+    pub(crate) fn at_line(self, line: usize) -> Self {
+        DecodeError::Line {
+            line,
+            error: Box::new(self),
         }
-        n -= 1;
     }
+}
 
-    let mut digits = Vec::with_capacity(n);
-    for c in s.chars().take(n) {
-        digits.push(u8_from_base64(c)/*.context(format!("not a valid base64 string: {}", s))*/?);
-    }
+// A binary-to-text encoding. `decode` only accepts the output of `encode`,
+// while `decode_lenient` ignores whitespace, missing padding and similar
+// deviations which don't change the decoded bytes.
+pub trait Encoding {
+    fn encode(&self, input: &[u8]) -> String;
+    fn decode(&self, input: &str) -> Result<Vec<u8>>;
+    fn decode_lenient(&self, input: &str) -> Result<Vec<u8>>;
+}
 
-    let mut u = Vec::with_capacity(3 * s.len() / 4);
-    for b in digits.chunks(4) {
-        u.push((b[0] << 2) + (b[1] >> 4));
-        if b.len() == 2 {
-            if b[1] << 4 != 0 {
-                return Err("input not padded with zero".into());
-            }
-            break;
-        }
+pub trait Serialize {
+    fn to_base64(&self) -> String;
+    fn to_hex(&self) -> String;
+}
 
-        u.push((b[1] << 4) + (b[2] >> 2));
-        if b.len() == 3 {
-            if b[2] << 6 != 0 {
-                return Err("input not padded with zero".into());
-            }
-            break;
-        }
+impl Serialize for [u8] {
+    fn to_base64(&self) -> String {
+        BASE64.encode(self)
+    }
 
-        u.push((b[2] << 6) + b[3]);
+    fn to_hex(&self) -> String {
+        HEX.encode(self)
     }
-    Ok(u)
+}
+
+pub fn from_base64(s: &str) -> Result<Vec<u8>> {
+    BASE64.decode(s)
 }
 
 pub fn from_base64_file(path: &Path) -> Result<Vec<u8>> {
-    let mut content = String::new();
-    let file = File::open(&path)?;
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
-    for line in reader.lines() {
-        content.push_str(line.unwrap().trim());
+    let mut lines = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        lines.push((
+            i + 1,
+            line.map_err(|err| DecodeError::from(err).at_line(i + 1))?,
+        ));
     }
-    from_base64(&content)
+    decode_lines(&lines, &BASE64)
+}
+
+// Decodes the concatenation of the trimmed lines, which are given together
+// with their line numbers. Errors are reported at the line where they occur,
+// errors without an offset at the last line.
+pub(crate) fn decode_lines<S: AsRef<str>>(
+    lines: &[(usize, S)],
+    encoding: &dyn Encoding,
+) -> Result<Vec<u8>> {
+    let mut content = String::new();
+    // For each line, the offset of its trimmed content in `content` and in
+    // the line itself.
+    let mut line_offsets = Vec::with_capacity(lines.len());
+    for (_, line) in lines {
+        let line = line.as_ref();
+        let leading_whitespace = line.len() - line.trim_start().len();
+        line_offsets.push((content.len(), leading_whitespace));
+        content.push_str(line.trim());
+    }
+
+    encoding.decode(&content).map_err(|err| {
+        let offset = err.offset().unwrap_or(content.len());
+        let i = line_offsets
+            .partition_point(|&(start, _)| start <= offset)
+            .saturating_sub(1);
+        match (lines.get(i), line_offsets.get(i)) {
+            (Some(&(line, _)), Some(&(start, leading_whitespace))) => err
+                .map_offset(|offset| offset - start + leading_whitespace)
+                .at_line(line),
+            _ => err,
+        }
+    })
 }
 
 pub fn from_base64_lines(path: &Path) -> Result<Vec<Vec<u8>>> {
@@ -103,67 +206,40 @@ pub fn from_hex_lines(path: &Path) -> Result<Vec<Vec<u8>>> {
 
 fn from_lines(path: &Path, converter: fn(&str) -> Result<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
     let mut content = Vec::new();
-    let file = File::open(&path)?;
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
-    for line in reader.lines() {
-        content.push(converter(line.unwrap().trim())?);
+    for (i, line) in reader.lines().enumerate() {
+        let decoded = line.map_err(DecodeError::from).and_then(|line| {
+            let leading_whitespace = line.len() - line.trim_start().len();
+            converter(line.trim())
+                .map_err(|err| err.map_offset(|offset| offset + leading_whitespace))
+        });
+        content.push(decoded.map_err(|err| err.at_line(i + 1))?);
     }
     Ok(content)
 }
 
 pub fn from_hex(s: &str) -> Result<Vec<u8>> {
-    if s.len() % 2 != 0 {
-        return Err("input length needs to be multiple of 2".into());
-    }
-
-    let mut digits = Vec::with_capacity(s.len());
-    for c in s.chars() {
-        digits.push(u8_from_hex(c)/*.context(format!("not a valid hex string: {}", s))*/?);
-    }
-    Ok(digits
-        .chunks(2)
-        .map(|c| (c[0] << 4) + c[1])
-        .collect::<Vec<u8>>())
-}
-
-fn u8_from_hex(c: char) -> Result<u8> {
-    match c.to_digit(16) {
-        Some(i) => Ok(i as u8),
-        _ => Err(format!("invalid character {}", c).into()),
-    }
+    HEX.decode(s)
 }
 
-fn block_to_base64(block: &[u8], base64: &mut String) {
-    let (a, b, c) = match block.len() {
-        3 => (block[0], block[1], block[2]),
-        2 => (block[0], block[1], 0),
-        1 => (block[0], 0, 0),
-        _ => return,
-    };
-    base64.push(u8_to_base64(a >> 2)); // Upper 6 bits of a
-    base64.push(u8_to_base64(a % 4 * 16 + (b >> 4))); // Lower 2 bits of a, upper 4 bits of b
-    base64.push(u8_to_base64(b % 16 * 4 + (c >> 6))); // Lower 4 bits of b, upper 2 bits of c
-    base64.push(u8_to_base64(c & 0x3f)); // Lower 6 bits of c
-}
-
-fn u8_to_base64(u: u8) -> char {
-    match u {
-        0..=25 => (b'A' + u) as char,
-        26..=51 => (b'a' + (u - 26)) as char,
-        52..=61 => (b'0' + (u - 52)) as char,
-        62 => '+',
-        63 => '/',
-        _ => panic!("input exceeded range"),
+#[test]
+fn test_file_errors() {
+    let path = std::env::temp_dir().join(format!("serialize-test-{}", std::process::id()));
+    std::fs::write(&path, "Zm9v\n  YmFy\n Zm9*YmFy\n").unwrap();
+    match from_base64_file(&path) {
+        Err(DecodeError::Line { line: 3, error }) => match *error {
+            DecodeError::InvalidCharacter {
+                character: '*',
+                offset: 4,
+            } => {}
+            err => panic!("unexpected error {}", err),
+        },
+        result => panic!("unexpected result {:?}", result),
     }
-}
 
-fn u8_from_base64(c: char) -> Result<u8> {
-    match c {
-        'A'..='Z' => Ok(c as u8 - b'A'),
-        'a'..='z' => Ok(26 + (c as u8 - b'a')),
-        '0'..='9' => Ok(52 + (c as u8 - b'0')),
-        '+' => Ok(62),
-        '/' => Ok(63),
-        _ => Err(format!("invalid character {}", c).into()),
-    }
+    std::fs::write(&path, "abcd\n  0f0\n").unwrap();
+    let err = from_hex_lines(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!("line 2: invalid input length 3", err.to_string());
 }
@@ -0,0 +1,226 @@
+// The base 64, base 32 and base 16 encodings from RFC 4648. Each character
+// encodes a fixed number of bits, and padded encodings fill up the last group
+// of characters with '='.
+
+use crate::{DecodeError, Encoding, Result};
+
+const PADDING: char = '=';
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rfc4648 {
+    alphabet: &'static [u8],
+    bits: u32,
+    // The number of characters which encode a whole number of bytes.
+    group_len: usize,
+    padding: bool,
+    case_insensitive: bool,
+}
+
+static BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+static BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+static BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+static HEX_ALPHABET: &[u8; 16] = b"0123456789abcdef";
+
+pub const BASE64: Rfc4648 = Rfc4648::base64(BASE64_ALPHABET, true);
+pub const BASE64_NOPAD: Rfc4648 = Rfc4648::base64(BASE64_ALPHABET, false);
+pub const BASE64URL: Rfc4648 = Rfc4648::base64(BASE64URL_ALPHABET, true);
+pub const BASE64URL_NOPAD: Rfc4648 = Rfc4648::base64(BASE64URL_ALPHABET, false);
+pub const BASE32: Rfc4648 = Rfc4648::base32(true);
+pub const BASE32_NOPAD: Rfc4648 = Rfc4648::base32(false);
+
+// Encodes to lower case and decodes both cases.
+pub const HEX: Rfc4648 = Rfc4648 {
+    alphabet: HEX_ALPHABET,
+    bits: 4,
+    group_len: 2,
+    padding: false,
+    case_insensitive: true,
+};
+
+impl Rfc4648 {
+    const fn base64(alphabet: &'static [u8; 64], padding: bool) -> Self {
+        Rfc4648 {
+            alphabet,
+            bits: 6,
+            group_len: 4,
+            padding,
+            case_insensitive: false,
+        }
+    }
+
+    const fn base32(padding: bool) -> Self {
+        Rfc4648 {
+            alphabet: BASE32_ALPHABET,
+            bits: 5,
+            group_len: 8,
+            padding,
+            case_insensitive: false,
+        }
+    }
+
+    // The number of characters in a group.
+    pub(crate) fn group_len(&self) -> usize {
+        self.group_len
+    }
+
+    // The number of bytes encoded by a group.
+    pub(crate) fn group_size(&self) -> usize {
+        self.group_len * self.bits as usize / 8
+    }
+
+    fn decoding_table(&self) -> [Option<u8>; 256] {
+        let mut table = [None; 256];
+        for (i, &c) in self.alphabet.iter().enumerate() {
+            table[c as usize] = Some(i as u8);
+            if self.case_insensitive {
+                table[c.to_ascii_uppercase() as usize] = Some(i as u8);
+            }
+        }
+        table
+    }
+
+    fn decode_with(&self, input: &str, lenient: bool) -> Result<Vec<u8>> {
+        let table = self.decoding_table();
+        let mut output = Vec::with_capacity(input.len() * self.bits as usize / 8);
+        let mut buffer = 0u32;
+        let mut buffered = 0;
+        let mut digits = 0;
+        let mut padding = 0;
+        for (offset, c) in input.char_indices() {
+            if lenient && c.is_ascii_whitespace() {
+                continue;
+            }
+            if c == PADDING {
+                padding += 1;
+                continue;
+            }
+
+            let value = match table.get(c as usize) {
+                Some(&Some(value)) if padding == 0 => value,
+                _ => {
+                    return Err(DecodeError::InvalidCharacter {
+                        character: c,
+                        offset,
+                    })
+                }
+            };
+            buffer = buffer << self.bits | u32::from(value);
+            buffered += self.bits;
+            if buffered >= 8 {
+                buffered -= 8;
+                output.push((buffer >> buffered) as u8);
+            }
+            digits += 1;
+        }
+
+        let remainder = digits % self.group_len;
+        if remainder * self.bits as usize % 8 >= self.bits as usize {
+            return Err(DecodeError::InvalidLength {
+                length: digits + padding,
+            });
+        }
+        if lenient {
+            return Ok(output);
+        }
+
+        if buffer & ((1 << buffered) - 1) != 0 {
+            return Err(DecodeError::NonZeroPadding);
+        }
+        let expected_padding = if self.padding && remainder > 0 {
+            self.group_len - remainder
+        } else {
+            0
+        };
+        if padding == 0 && expected_padding > 0 {
+            return Err(DecodeError::Truncated);
+        }
+        if padding != expected_padding {
+            return Err(DecodeError::InvalidLength {
+                length: digits + padding,
+            });
+        }
+        Ok(output)
+    }
+}
+
+impl Encoding for Rfc4648 {
+    fn encode(&self, input: &[u8]) -> String {
+        let mut output = String::with_capacity((8 * input.len()).div_ceil(self.bits as usize));
+        let mask = (1 << self.bits) - 1;
+        let mut buffer = 0u32;
+        let mut buffered = 0;
+        for &u in input {
+            buffer = buffer << 8 | u32::from(u);
+            buffered += 8;
+            while buffered >= self.bits {
+                buffered -= self.bits;
+                output.push(self.alphabet[(buffer >> buffered & mask) as usize] as char);
+            }
+        }
+
+        // The remaining bits are padded with zero to a whole character.
+        if buffered > 0 {
+            output.push(self.alphabet[(buffer << (self.bits - buffered) & mask) as usize] as char);
+        }
+        if self.padding {
+            while !output.len().is_multiple_of(self.group_len) {
+                output.push(PADDING);
+            }
+        }
+        output
+    }
+
+    fn decode(&self, input: &str) -> Result<Vec<u8>> {
+        self.decode_with(input, false)
+    }
+
+    fn decode_lenient(&self, input: &str) -> Result<Vec<u8>> {
+        self.decode_with(input, true)
+    }
+}
+
+#[test]
+fn test_rfc4648() {
+    // The test vectors from section 10 of RFC 4648.
+    let inputs = ["", "f", "fo", "foo", "foob", "fooba", "foobar"];
+    let base64 = [
+        "", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy",
+    ];
+    let base32 = [
+        "",
+        "MY======",
+        "MZXQ====",
+        "MZXW6===",
+        "MZXW6YQ=",
+        "MZXW6YTB",
+        "MZXW6YTBOI======",
+    ];
+    for (i, input) in inputs.iter().enumerate() {
+        let input = input.as_bytes();
+        assert_eq!(base64[i], BASE64.encode(input));
+        assert_eq!(input, &BASE64.decode(base64[i]).unwrap()[..]);
+        assert_eq!(base32[i], BASE32.encode(input));
+        assert_eq!(input, &BASE32.decode(base32[i]).unwrap()[..]);
+
+        let unpadded = base32[i].trim_end_matches('=');
+        assert_eq!(unpadded, BASE32_NOPAD.encode(input));
+        assert_eq!(input, &BASE32.decode_lenient(unpadded).unwrap()[..]);
+        assert_eq!(input, &HEX.decode(&HEX.encode(input)).unwrap()[..]);
+    }
+
+    let input = [0xfb, 0xff, 0xbf];
+    assert_eq!("-_-_", BASE64URL.encode(&input));
+    assert_eq!("-_8", BASE64URL_NOPAD.encode(&[0xfb, 0xff]));
+    assert!(BASE64.decode("-_-_").is_err());
+    assert!(BASE64URL.decode("-_8").is_err());
+    assert_eq!(vec![0xfb, 0xff], BASE64URL.decode_lenient("-_8").unwrap());
+
+    assert!(BASE64.decode("Zm9v\nYmFy").is_err());
+    assert_eq!(b"foobar", &BASE64.decode_lenient("Zm9v\nYmFy").unwrap()[..]);
+    assert!(BASE64.decode("Zh==").is_err());
+    assert_eq!(b"f", &BASE64.decode_lenient("Zh").unwrap()[..]);
+    assert!(BASE64.decode_lenient("Zm9vY").is_err());
+    assert_eq!(vec![0xab, 0xcd], HEX.decode("AbCd").unwrap());
+}
@@ -0,0 +1,244 @@
+// Streaming encoders and decoders for the RFC 4648 encodings. Only incomplete
+// groups of characters are buffered, so the input can be split at arbitrary
+// positions and streams of any length are processed in constant memory.
+//
+// Decoders skip line breaks and otherwise decode strictly. Offsets in their
+// errors count the characters read without line breaks.
+
+use std::cmp;
+use std::io;
+use std::io::{Read, Write};
+
+use crate::{DecodeError, Encoding, Rfc4648, BASE64, HEX};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+impl From<DecodeError> for io::Error {
+    fn from(err: DecodeError) -> io::Error {
+        match err {
+            DecodeError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+// Encodes everything written to it and passes the characters on to `inner`.
+// The last, possibly padded group is only written by `finish`, which
+// therefore always needs to be called.
+pub struct Encoder<W: Write> {
+    inner: W,
+    encoding: Rfc4648,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, encoding: Rfc4648) -> Self {
+        Encoder {
+            inner,
+            encoding,
+            buffer: Vec::with_capacity(encoding.group_size()),
+        }
+    }
+
+    // Encodes the remaining buffered bytes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner
+            .write_all(self.encoding.encode(&self.buffer).as_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        let full_len = self.buffer.len() - self.buffer.len() % self.encoding.group_size();
+        let encoded = self.encoding.encode(&self.buffer[..full_len]);
+        self.buffer.drain(..full_len);
+        self.inner.write_all(encoded.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    // Incomplete groups cannot be flushed before the stream is finished.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Decodes the characters read from `inner`. Whole groups are decoded as soon
+// as they are read, the last group when `inner` reaches EOF.
+pub struct Decoder<R: Read> {
+    inner: R,
+    encoding: Rfc4648,
+    encoded: Vec<u8>,
+    decoded: Vec<u8>,
+    position: usize,
+    // The number of characters decoded so far.
+    offset: usize,
+    // Set after a group with padding, which has to be the last one.
+    padded: bool,
+    eof: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R, encoding: Rfc4648) -> Self {
+        Decoder {
+            inner,
+            encoding,
+            encoded: Vec::with_capacity(READ_CHUNK_SIZE),
+            decoded: Vec::with_capacity(READ_CHUNK_SIZE),
+            position: 0,
+            offset: 0,
+            padded: false,
+            eof: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_decoded(&mut self) -> io::Result<()> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let n = self.inner.read(&mut chunk)?;
+        self.decoded.clear();
+        self.position = 0;
+
+        self.encoded
+            .extend(chunk[..n].iter().filter(|&&c| c != b'\n' && c != b'\r'));
+        let len = if n == 0 {
+            self.encoded.len()
+        } else {
+            self.encoded.len() - self.encoded.len() % self.encoding.group_len()
+        };
+        self.decoded = self.decode_groups(len)?;
+        // decode_groups leaves the buffer untouched on errors, so setting eof
+        // only now makes later reads return the error again.
+        self.eof = n == 0;
+        Ok(())
+    }
+
+    // Decodes and removes the first `len` buffered characters.
+    fn decode_groups(&mut self, len: usize) -> Result<Vec<u8>, DecodeError> {
+        let encoded = &self.encoded[..len];
+        let offset = self.offset;
+        let invalid_character = |i: usize| DecodeError::InvalidCharacter {
+            character: char::from(encoded[i]),
+            offset: offset + i,
+        };
+        if self.padded && len > 0 {
+            return Err(invalid_character(0));
+        }
+        if let Some(i) = encoded.iter().position(|c| !c.is_ascii()) {
+            return Err(invalid_character(i));
+        }
+
+        let decoded = self
+            .encoding
+            .decode(std::str::from_utf8(encoded).unwrap()) // unwrap is ok, the input is ASCII
+            .map_err(|err| err.map_offset(|o| offset + o))?;
+        self.padded = encoded.last() == Some(&b'=');
+        self.offset += len;
+        self.encoded.drain(..len);
+        Ok(decoded)
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() && !self.eof {
+            self.fill_decoded()?;
+        }
+
+        let n = cmp::min(buf.len(), self.decoded.len() - self.position);
+        buf[..n].copy_from_slice(&self.decoded[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+// Named encoders and decoders for the common cases.
+macro_rules! streaming_encoding {
+    ($encoder:ident, $decoder:ident, $encoding:expr) => {
+        pub struct $encoder<W: Write>(Encoder<W>);
+
+        impl<W: Write> $encoder<W> {
+            pub fn new(inner: W) -> Self {
+                $encoder(Encoder::new(inner, $encoding))
+            }
+
+            pub fn finish(self) -> io::Result<W> {
+                self.0.finish()
+            }
+        }
+
+        impl<W: Write> Write for $encoder<W> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.0.flush()
+            }
+        }
+
+        pub struct $decoder<R: Read>(Decoder<R>);
+
+        impl<R: Read> $decoder<R> {
+            pub fn new(inner: R) -> Self {
+                $decoder(Decoder::new(inner, $encoding))
+            }
+
+            pub fn into_inner(self) -> R {
+                self.0.into_inner()
+            }
+        }
+
+        impl<R: Read> Read for $decoder<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+    };
+}
+
+streaming_encoding!(Base64Encoder, Base64Decoder, BASE64);
+streaming_encoding!(HexEncoder, HexDecoder, HEX);
+
+#[test]
+fn test_streaming() {
+    let input: Vec<u8> = (0..=255).cycle().take(10000).collect();
+    let base64 = BASE64.encode(&input);
+    for &chunk_size in &[1, 2, 5, 4096] {
+        let mut encoder = Base64Encoder::new(Vec::new());
+        for chunk in input.chunks(chunk_size) {
+            encoder.write_all(chunk).unwrap();
+        }
+        assert_eq!(base64.as_bytes(), &encoder.finish().unwrap()[..]);
+    }
+
+    // Line breaks every 76 characters, as in MIME.
+    let wrapped: Vec<u8> = base64
+        .as_bytes()
+        .chunks(76)
+        .flat_map(|line| line.iter().chain(b"\r\n"))
+        .cloned()
+        .collect();
+    let mut decoded = Vec::new();
+    Base64Decoder::new(&wrapped[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(input, decoded);
+
+    let mut decoded = Vec::new();
+    HexDecoder::new(&b"00ff\n10"[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(vec![0x00, 0xff, 0x10], decoded);
+    let mut decoder = HexDecoder::new(&b"00f"[..]);
+    assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    assert!(decoder.read(&mut [0; 4]).is_err());
+    assert!(Base64Decoder::new(&b"Zg==\nZg=="[..])
+        .read_to_end(&mut Vec::new())
+        .is_err());
+}
@@ -0,0 +1,291 @@
+// Cryptanalysis of XOR ciphers with a single byte or a repeating key, assuming
+// that the cleartext is English text.
+//
+// Candidates are ranked by `compute_score`, where smaller scores are better.
+// Repeating keys are broken by first determining likely key sizes, using the
+// normalized Hamming distance, the index of coincidence and Kasiski
+// examination, and then breaking each column of the ciphertext as a single
+// byte XOR.
+
+use crate::{XorError, XOR};
+
+use std::collections::HashMap;
+
+// A reasonable maximum key size for `break_multibyte_xor`.
+pub const DEFAULT_MAX_KEYSIZE: usize = 40;
+
+// The number of key sizes taken from each detection method.
+const KEYSIZES_PER_METHOD: usize = 3;
+
+// Source:
+// Lee, E. Stewart. "Essays about Computer Security" (PDF). University of Cambridge Computer Laboratory. p. 181.
+static EXPECTED_FREQUENCIES: [(u8, f32); 28] = [
+    (b' ', 12.17), // Whitespace
+    (b'.', 6.57),  // Others
+    (b'a', 6.09),
+    (b'b', 1.05),
+    (b'c', 2.84),
+    (b'd', 2.92),
+    (b'e', 11.36),
+    (b'f', 1.79),
+    (b'g', 1.38),
+    (b'h', 3.41),
+    (b'i', 5.44),
+    (b'j', 0.24),
+    (b'k', 0.41),
+    (b'l', 2.92),
+    (b'm', 2.76),
+    (b'n', 5.44),
+    (b'o', 6.00),
+    (b'p', 1.95),
+    (b'q', 0.24),
+    (b'r', 4.95),
+    (b's', 5.68),
+    (b't', 8.03),
+    (b'u', 2.43),
+    (b'v', 0.97),
+    (b'w', 1.38),
+    (b'x', 0.24),
+    (b'y', 1.30),
+    (b'z', 0.03),
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Candidate<K> {
+    pub key: K,
+    pub score: u32,
+}
+
+fn is_control(u: u8) -> bool {
+    u < 0x20 || u == 0x7F
+}
+
+fn get_character_counts(v: &[u8]) -> HashMap<u8, f32> {
+    let mut counts: HashMap<u8, f32> = HashMap::new();
+    for &c in v.iter() {
+        if is_control(c) {
+            continue;
+        }
+        let key = if c.is_ascii_alphabetic() {
+            c.to_ascii_lowercase()
+        } else if c == b' ' || c == b'\t' {
+            b' '
+        } else {
+            b'.'
+        };
+
+        let count = counts.entry(key).or_insert(0f32);
+        *count += 1f32;
+    }
+    counts
+}
+
+// The squared distance between the character counts of v and those of English
+// text of the same length. Inputs which are not printable ASCII get the score
+// u32::MAX.
+pub fn compute_score(v: &[u8]) -> u32 {
+    if !v.is_ascii() {
+        return u32::MAX;
+    }
+
+    if v.iter().any(|&c| is_control(c) && c != b'\n') {
+        return u32::MAX;
+    }
+
+    let counts = get_character_counts(v);
+    let length = v.len() as f32;
+
+    EXPECTED_FREQUENCIES.iter().fold(0f32, |a, &(c, score)| {
+        let expected_count = score / 100f32 * length;
+        let &actual_count = counts.get(&c).unwrap_or(&0f32);
+        a + (expected_count - actual_count).powi(2)
+    }) as u32
+}
+
+// All 256 keys, best first.
+pub fn rank_single_byte_keys(input: &[u8]) -> Vec<Candidate<u8>> {
+    // We consider arbitrary bytes here because of challenges 19 and 20.
+    let mut candidates: Vec<Candidate<u8>> = (0u8..=255)
+        .map(|key| Candidate {
+            key,
+            score: compute_score(&input.xor_repeating(&[key]).unwrap()), // unwrap is ok
+        })
+        .collect();
+    candidates.sort_by_key(|c| c.score);
+    candidates
+}
+
+pub fn break_single_byte_xor(input: &[u8]) -> u8 {
+    rank_single_byte_keys(input)[0].key
+}
+
+pub fn hamming_distance(u: &[u8], v: &[u8]) -> Result<u32, XorError> {
+    Ok(u.xor_exact(v)?.iter().map(|b| b.count_ones()).sum())
+}
+
+// The probability that two randomly chosen bytes of u are equal. It is about
+// 0.065 for English text and 1/256 for uniformly random bytes.
+pub fn index_of_coincidence(u: &[u8]) -> f64 {
+    if u.len() < 2 {
+        return 0.0;
+    }
+
+    let mut counts = [0u64; 256];
+    for &b in u {
+        counts[b as usize] += 1;
+    }
+    let n = u.len() as f64;
+    counts
+        .iter()
+        .map(|&c| (c * c.saturating_sub(1)) as f64)
+        .sum::<f64>()
+        / (n * (n - 1.0))
+}
+
+// Key sizes ordered by the Hamming distance between the first blocks of
+// input, normalized by the key size. Key sizes for which input does not
+// contain at least two blocks are skipped.
+pub fn keysizes_by_hamming_distance(input: &[u8], max_keysize: usize) -> Vec<usize> {
+    let mut distances: Vec<(usize, u32)> = (1..=max_keysize)
+        .filter(|&keysize| input.len() >= 2 * keysize)
+        .map(|keysize| (keysize, normalized_hamming_distance(input, keysize)))
+        .collect();
+    distances.sort_by_key(|&(_, distance)| distance);
+    distances.into_iter().map(|(keysize, _)| keysize).collect()
+}
+
+// We use up to the first four blocks of `input` and average the pairwise
+// distances.
+fn normalized_hamming_distance(input: &[u8], keysize: usize) -> u32 {
+    let blocks: Vec<&[u8]> = input.chunks_exact(keysize).take(4).collect();
+    let mut distance = 0;
+    let mut pairs = 0;
+    for i in 0..blocks.len() {
+        for j in i + 1..blocks.len() {
+            distance += hamming_distance(blocks[i], blocks[j]).unwrap(); // unwrap is ok
+            pairs += 1;
+        }
+    }
+    (100 * distance as usize / (pairs * keysize)) as u32
+}
+
+// Key sizes ordered by the average index of coincidence of the columns of
+// input. For the correct key size each column is a single byte XOR of text,
+// which does not change the index of coincidence.
+pub fn keysizes_by_index_of_coincidence(input: &[u8], max_keysize: usize) -> Vec<usize> {
+    let mut indices: Vec<(usize, f64)> = (1..=max_keysize)
+        .filter(|&keysize| input.len() >= 2 * keysize)
+        .map(|keysize| {
+            let columns = transposed_blocks(input, keysize);
+            let sum: f64 = columns.iter().map(|c| index_of_coincidence(c)).sum();
+            (keysize, sum / keysize as f64)
+        })
+        .collect();
+    indices.sort_by(|(_, x), (_, y)| y.total_cmp(x));
+    indices.into_iter().map(|(keysize, _)| keysize).collect()
+}
+
+// Kasiski examination: Repeated trigrams in the ciphertext are likely
+// encryptions of the same cleartext under the same part of the key, so their
+// distance is a multiple of the key size. Each key size is scored by the
+// number of distances it divides, weighted by the key size to compensate for
+// small key sizes dividing more distances by chance.
+pub fn keysizes_by_kasiski(input: &[u8], max_keysize: usize) -> Vec<usize> {
+    let mut positions: HashMap<&[u8], usize> = HashMap::new();
+    let mut distances = Vec::new();
+    for (i, trigram) in input.windows(3).enumerate() {
+        if let Some(previous) = positions.insert(trigram, i) {
+            distances.push(i - previous);
+        }
+    }
+
+    let mut scores: Vec<(usize, usize)> = (1..=max_keysize)
+        .map(|keysize| {
+            let count = distances.iter().filter(|&&d| d % keysize == 0).count();
+            (keysize, count * keysize)
+        })
+        .collect();
+    scores.sort_by(|(_, x), (_, y)| y.cmp(x));
+    scores.into_iter().map(|(keysize, _)| keysize).collect()
+}
+
+// The best key sizes according to each of the detection methods, without
+// duplicates.
+pub fn candidate_keysizes(input: &[u8], max_keysize: usize) -> Vec<usize> {
+    let rankings = [
+        keysizes_by_hamming_distance(input, max_keysize),
+        keysizes_by_index_of_coincidence(input, max_keysize),
+        keysizes_by_kasiski(input, max_keysize),
+    ];
+
+    let mut keysizes = Vec::new();
+    for ranking in rankings.iter() {
+        for &keysize in ranking.iter().take(KEYSIZES_PER_METHOD) {
+            if !keysizes.contains(&keysize) {
+                keysizes.push(keysize);
+            }
+        }
+    }
+    keysizes
+}
+
+fn transposed_blocks(input: &[u8], size: usize) -> Vec<Vec<u8>> {
+    let mut transposed_blocks: Vec<Vec<u8>> = (0..size).map(|_| Vec::new()).collect();
+    for block in input.chunks(size) {
+        for (&u, bt) in block.iter().zip(transposed_blocks.iter_mut()) {
+            bt.push(u);
+        }
+    }
+    transposed_blocks
+}
+
+pub fn break_multibyte_xor_for_keysize(input: &[u8], keysize: usize) -> Vec<u8> {
+    transposed_blocks(input, keysize)
+        .iter()
+        .map(|b| break_single_byte_xor(b))
+        .collect::<Vec<u8>>()
+}
+
+// One key for each candidate key size, best first. To pick the correct key
+// from the different candidates we use our scoring function based on
+// character frequencies. Multiples of the correct key size give the same
+// cleartext, so ties are resolved in favour of shorter keys.
+pub fn break_multibyte_xor(input: &[u8], max_keysize: usize) -> Vec<Candidate<Vec<u8>>> {
+    let mut candidates: Vec<Candidate<Vec<u8>>> = candidate_keysizes(input, max_keysize)
+        .into_iter()
+        .map(|keysize| {
+            let key = break_multibyte_xor_for_keysize(input, keysize);
+            let score = compute_score(&input.xor_repeating(&key).unwrap()); // unwrap is ok
+            Candidate { key, score }
+        })
+        .collect();
+    candidates.sort_by_key(|c| (c.score, c.key.len()));
+    candidates
+}
+
+#[test]
+fn test_hamming_distance() {
+    assert_eq!(
+        37,
+        hamming_distance(b"this is a test", b"wokka wokka!!!").unwrap() // unwrap is ok
+    );
+    assert!(hamming_distance(b"this is a test", b"wokka").is_err());
+}
+
+#[test]
+fn test_break_multibyte_xor() {
+    let cleartext = b"It was the best of times, it was the worst of times, it was the age of \
+        wisdom, it was the age of foolishness, it was the epoch of belief, it was the epoch \
+        of incredulity, it was the season of Light, it was the season of Darkness, it was \
+        the spring of hope, it was the winter of despair, we had everything before us, we \
+        had nothing before us, we were all going direct to Heaven, we were all going direct \
+        the other way - in short, the period was so far like the present period, that some \
+        of its noisiest authorities insisted on its being received, for good or for evil, \
+        in the superlative degree of comparison only.";
+    let key = b"Dickens";
+    let ciphertext = cleartext.xor(key);
+
+    assert!(candidate_keysizes(&ciphertext, DEFAULT_MAX_KEYSIZE).contains(&key.len()));
+    assert_eq!(key.to_vec(), break_multibyte_xor(&ciphertext, 20)[0].key);
+    assert_eq!(b'D', rank_single_byte_keys(&cleartext.xor(b"D"))[0].key);
+}
@@ -1,6 +1,59 @@
+// XOR of byte slices. There are three variants which differ in how operands
+// of different length are treated:
+//
+// - repeating: the right operand is a key which is repeated to the length of
+//   the left operand. An empty key is an error.
+// - truncating: the result has the length of the shorter operand.
+// - exact: both operands need to have the same length.
+//
+// `xor` and `xor_inplace` are the repeating variant and panic if the key is
+// empty. The bulk of the work is done on u128 words.
+
+pub mod analysis;
+
+use std::error;
+use std::fmt;
+
+const WORD_SIZE: usize = 16;
+
+// Short keys are repeated to at least this length before the main loop, so
+// that it can work on whole words.
+const MIN_EXPANDED_KEY_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum XorError {
+    EmptyKey,
+    LengthMismatch { left: usize, right: usize },
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for XorError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl fmt::Display for XorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XorError::EmptyKey => write!(f, "key is empty"),
+            XorError::LengthMismatch { left, right } => {
+                write!(f, "operand lengths {} and {} differ", left, right)
+            }
+        }
+    }
+}
+
 pub trait XOR {
     fn xor(&self, _: &Self) -> Vec<u8>;
     fn xor_inplace(&mut self, _: &Self);
+
+    fn xor_repeating(&self, key: &Self) -> Result<Vec<u8>, XorError>;
+    fn xor_repeating_inplace(&mut self, key: &Self) -> Result<(), XorError>;
+    fn xor_truncating(&self, _: &Self) -> Vec<u8>;
+    fn xor_exact(&self, _: &Self) -> Result<Vec<u8>, XorError>;
+    fn xor_exact_inplace(&mut self, _: &Self) -> Result<(), XorError>;
 }
 
 impl XOR for [u8] {
@@ -11,11 +64,121 @@ impl XOR for [u8] {
     }
 
     fn xor_inplace(&mut self, t: &[u8]) {
-        for chunk in self.chunks_mut(t.len()) {
+        if let Err(err) = self.xor_repeating_inplace(t) {
+            panic!("xor failed: {}", err);
+        }
+    }
+
+    fn xor_repeating(&self, key: &[u8]) -> Result<Vec<u8>, XorError> {
+        let mut result = self.to_vec();
+        result.xor_repeating_inplace(key)?;
+        Ok(result)
+    }
+
+    fn xor_repeating_inplace(&mut self, key: &[u8]) -> Result<(), XorError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if key.is_empty() {
+            return Err(XorError::EmptyKey);
+        }
+
+        // The expanded key is a multiple of the key length, so chunks of its
+        // length start at the beginning of the key.
+        let expanded_key;
+        let key = if key.len() < MIN_EXPANDED_KEY_SIZE && key.len() < self.len() {
+            let repetitions = MIN_EXPANDED_KEY_SIZE.div_ceil(key.len());
+            expanded_key = key.repeat(repetitions);
+            &expanded_key[..]
+        } else {
+            key
+        };
+
+        for chunk in self.chunks_mut(key.len()) {
             let len = chunk.len();
-            for (c, &d) in chunk.iter_mut().zip(t[..len].iter()) {
-                *c ^= d;
-            }
+            xor_words(chunk, &key[..len]);
         }
+        Ok(())
+    }
+
+    fn xor_truncating(&self, t: &[u8]) -> Vec<u8> {
+        let len = self.len().min(t.len());
+        let mut result = self[..len].to_vec();
+        xor_words(&mut result, &t[..len]);
+        result
+    }
+
+    fn xor_exact(&self, t: &[u8]) -> Result<Vec<u8>, XorError> {
+        let mut result = self.to_vec();
+        result.xor_exact_inplace(t)?;
+        Ok(result)
     }
+
+    fn xor_exact_inplace(&mut self, t: &[u8]) -> Result<(), XorError> {
+        if self.len() != t.len() {
+            return Err(XorError::LengthMismatch {
+                left: self.len(),
+                right: t.len(),
+            });
+        }
+
+        xor_words(self, t);
+        Ok(())
+    }
+}
+
+// u and v need to have the same length.
+fn xor_words(u: &mut [u8], v: &[u8]) {
+    let mut u_words = u.chunks_exact_mut(WORD_SIZE);
+    let mut v_words = v.chunks_exact(WORD_SIZE);
+    for (x, y) in (&mut u_words).zip(&mut v_words) {
+        let z = to_word(x) ^ to_word(y);
+        x.copy_from_slice(&z.to_ne_bytes());
+    }
+    for (x, y) in u_words
+        .into_remainder()
+        .iter_mut()
+        .zip(v_words.remainder().iter())
+    {
+        *x ^= y;
+    }
+}
+
+fn to_word(u: &[u8]) -> u128 {
+    let mut bytes = [0; WORD_SIZE];
+    bytes.copy_from_slice(u);
+    u128::from_ne_bytes(bytes)
+}
+
+#[test]
+fn test_xor() {
+    let input: Vec<u8> = (0..=200).collect();
+    let key = b"ICE";
+    let expected: Vec<u8> = input
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| b ^ key[i % 3])
+        .collect();
+    assert_eq!(expected, input.xor(key));
+    assert_eq!(expected, input.xor_repeating(key).unwrap());
+    assert_eq!(&expected[..2], &input[..2].xor(key)[..]);
+    assert_eq!(XorError::EmptyKey, input.xor_repeating(&[]).unwrap_err());
+    assert!(b"".xor_repeating(&[]).unwrap().is_empty());
+
+    let long_key: Vec<u8> = (0..=200).rev().collect();
+    let expected: Vec<u8> = input
+        .iter()
+        .zip(long_key.iter())
+        .map(|(x, y)| x ^ y)
+        .collect();
+    assert_eq!(expected, input.xor_exact(&long_key).unwrap());
+    assert_eq!(&expected[..37], &input[..37].xor_truncating(&long_key)[..]);
+    assert_eq!(&expected[..37], &long_key.xor_truncating(&input[..37])[..]);
+    assert_eq!(
+        XorError::LengthMismatch {
+            left: 201,
+            right: 37
+        },
+        input.xor_exact(&long_key[..37]).unwrap_err()
+    );
 }